@@ -1,8 +1,8 @@
-use std::{io::BufWriter, path::PathBuf, str::FromStr};
+use std::{io::{self, BufWriter}, path::PathBuf, str::FromStr};
 
 use ariadne::{Report, ReportKind, Label, Source};
 use lang::{analyzer, ast, common::Error, lexer, parser, codegen};
-use isa::{risc, cisc};
+use isa::{risc, cisc, memory::Memory};
 use wasm_bindgen::prelude::*;
 
 fn create_pseudo_file(source: &str) -> Result<ast::File, Error> {
@@ -19,6 +19,19 @@ fn create_pseudo_file(source: &str) -> Result<ast::File, Error> {
     Ok(file)
 }
 
+// Blocks the code generators add for the language's built-in functions
+const BUILT_INS: &[&str] = &[
+    "print_i8", "print_i16", "print_i32", "print_i64",
+    "print_u8", "print_u16", "print_u32", "print_u64",
+    "print_f32", "print_f64", "print_bool",
+    "read_u64", "read_i64", "read_f64", "read_bool",
+    "exit",
+];
+
+fn is_built_in(label: &str) -> bool {
+    BUILT_INS.contains(&label)
+}
+
 #[wasm_bindgen]
 pub fn compile_risc(source: &str) -> Option<String> {
     let file = match create_pseudo_file(source) {
@@ -29,7 +42,7 @@ pub fn compile_risc(source: &str) -> Option<String> {
     let risc_blocks = codegen::risc::gen(&file);
     let mut risc_asm = String::new();
     for block in &risc_blocks {
-        if !block.label.starts_with("print_") {
+        if !is_built_in(&block.label) {
             risc_asm += &block.as_asm()
         }
     }
@@ -46,13 +59,25 @@ pub fn run_risc(source: &str) -> String {
 
     let risc_blocks = codegen::risc::gen(&file);
 
-    const MEMORY_SIZE: usize = 128_000; // 128 KiB
+    const MEMORY_SIZE: u64 = 128_000; // 128 KiB
 
     let mut stdout = Vec::<u8>::new();
-    let mut machine = risc::vm::VM::<_, MEMORY_SIZE>::new(&risc_blocks, &mut stdout);
-    machine.interpret();
+    let mut machine = risc::vm::VM::new(
+        &risc_blocks,
+        io::empty(),
+        &mut stdout,
+        Memory::flat(MEMORY_SIZE),
+    );
+    let status = machine.interpret();
+
+    let mut output = String::from_utf8(stdout).unwrap();
+    if let Some(err) = status.trap() {
+        output += &format!("{}\n", err);
+    } else if status.code != 0 {
+        output += &format!("exited with code {}\n", status.code);
+    }
 
-    String::from_utf8(stdout).unwrap()
+    output
 }
 
 #[wasm_bindgen]
@@ -65,7 +90,7 @@ pub fn compile_cisc(source: &str) -> Option<String> {
     let cisc_blocks = codegen::cisc::gen(&file);
     let mut cisc_asm = String::new();
     for block in &cisc_blocks {
-        if !block.label.starts_with("print_") {
+        if !is_built_in(&block.label) {
             cisc_asm += &block.as_asm()
         }
     }
@@ -82,13 +107,25 @@ pub fn run_cisc(source: &str) -> String {
 
     let cisc_blocks = codegen::cisc::gen(&file);
 
-    const MEMORY_SIZE: usize = 128_000; // 128 KiB
+    const MEMORY_SIZE: u64 = 128_000; // 128 KiB
 
     let mut stdout = Vec::<u8>::new();
-    let mut machine = cisc::vm::VM::<_, MEMORY_SIZE>::new(&cisc_blocks, &mut stdout);
-    machine.interpret();
+    let mut machine = cisc::vm::VM::new(
+        &cisc_blocks,
+        io::empty(),
+        &mut stdout,
+        Memory::flat(MEMORY_SIZE),
+    );
+    let status = machine.interpret();
+
+    let mut output = String::from_utf8(stdout).unwrap();
+    if let Some(err) = status.trap() {
+        output += &format!("{}\n", err);
+    } else if status.code != 0 {
+        output += &format!("exited with code {}\n", status.code);
+    }
 
-    String::from_utf8(stdout).unwrap()
+    output
 }
 
 fn build_error_report(source: &str, err: Error) -> String {
@@ -0,0 +1,160 @@
+use std::{error::Error, fmt};
+
+use crate::shared::{Imm, Label, Register};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// A single whitespace separated word of assembly, lines and columns start at 1
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Word<'a> {
+    pub text: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl<'a> Word<'a> {
+    pub fn error_at(&self, message: &str) -> AsmError {
+        AsmError {
+            message: message.into(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    pub fn expect_operands(&self, operands: &[Word], count: usize) -> Result<(), AsmError> {
+        if operands.len() == count {
+            Ok(())
+        } else {
+            Err(self.error_at(&format!(
+                "`{}` expects {} operand(s), found {}",
+                self.text,
+                count,
+                operands.len()
+            )))
+        }
+    }
+
+    pub fn register(&self) -> Result<Register, AsmError> {
+        if let Some(id) = self.text.strip_prefix('%') {
+            if let Ok(id) = id.parse::<u8>() {
+                if id <= Register::Rip.get_id() {
+                    return Ok(Register::from_id(id));
+                }
+            }
+        }
+
+        Err(self.error_at("expected register between `%0` and `%18`"))
+    }
+
+    pub fn label(&self) -> Result<Label, AsmError> {
+        match self.text.strip_prefix('@') {
+            Some(name) if is_label_name(name) => Ok(Label::new(name)),
+            _ => Err(self.error_at("expected label target like `@main`")),
+        }
+    }
+
+    pub fn imm(&self) -> Result<Imm, AsmError> {
+        let text = self.text;
+        let imm = match text {
+            "true" => Some(Imm::True),
+            "false" => Some(Imm::False),
+            _ if text.contains(['.', 'e', 'E']) || matches!(text, "NaN" | "inf" | "-inf") => {
+                text.parse::<f64>().ok().map(Imm::Float)
+            }
+            _ if text.starts_with('-') => text.parse::<i64>().ok().map(|i| Imm::Int(i as u64)),
+            _ => text.parse::<u64>().ok().map(Imm::Int),
+        };
+
+        imm.ok_or_else(|| self.error_at("expected immediate value"))
+    }
+}
+
+fn is_label_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '$')
+}
+
+fn split_words(text: &str, line: usize) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (column, (idx, c)) in text.char_indices().enumerate() {
+        if c.is_whitespace() {
+            if let Some((start_idx, start_column)) = start.take() {
+                words.push(Word {
+                    text: &text[start_idx..idx],
+                    line,
+                    column: start_column + 1,
+                });
+            }
+        } else if start.is_none() {
+            start = Some((idx, column));
+        }
+    }
+
+    if let Some((start_idx, start_column)) = start {
+        words.push(Word {
+            text: &text[start_idx..],
+            line,
+            column: start_column + 1,
+        });
+    }
+
+    words
+}
+
+// Splits assembly in the form printed by `Block::as_asm` into labelled blocks,
+// leaving the decoding of each instruction line to `parse_inst`
+pub(crate) fn parse_blocks<I>(
+    source: &str,
+    parse_inst: impl Fn(&Word, &[Word]) -> Result<I, AsmError>,
+) -> Result<Vec<(String, Vec<I>)>, AsmError> {
+    let mut blocks: Vec<(String, Vec<I>)> = Vec::new();
+
+    for (line_idx, text) in source.lines().enumerate() {
+        // Everything after a ';' is a comment
+        let text = text.split(';').next().unwrap_or("");
+        let words = split_words(text, line_idx + 1);
+
+        let (first, rest) = if let Some((first, rest)) = words.split_first() {
+            (first, rest)
+        } else {
+            continue;
+        };
+
+        if let Some(name) = first.text.strip_suffix(':') {
+            if !is_label_name(name) {
+                return Err(first.error_at("invalid block label"));
+            }
+            if let Some(extra) = rest.first() {
+                return Err(extra.error_at("unexpected token after block label"));
+            }
+            if blocks.iter().any(|(label, _)| label == name) {
+                return Err(first.error_at(&format!("duplicate block label `{}`", name)));
+            }
+
+            blocks.push((name.to_string(), Vec::new()));
+        } else if let Some((_, insts)) = blocks.last_mut() {
+            insts.push(parse_inst(first, rest)?);
+        } else {
+            return Err(first.error_at("instruction outside of a labelled block"));
+        }
+    }
+
+    Ok(blocks)
+}
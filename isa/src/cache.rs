@@ -0,0 +1,239 @@
+use crate::shared::Access;
+
+// A tag-only cache model. It doesn't hold any data, the VM's memory stays the
+// source of truth, it only tracks which lines would be resident to count hits
+// and misses.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplacementPolicy {
+    Lru,
+    Fifo,
+    // Pseudo random with a fixed seed so runs are reproducible
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePolicy {
+    WriteBack,
+    WriteThrough,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    // All sizes are in bytes and must be powers of two
+    pub size: u64,
+    pub line_size: u64,
+    pub associativity: u64,
+    pub replacement: ReplacementPolicy,
+    pub write_policy: WritePolicy,
+    // Whether a write miss brings the line into the cache
+    pub write_allocate: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            size: 4096,
+            line_size: 64,
+            associativity: 4,
+            replacement: ReplacementPolicy::Lru,
+            write_policy: WritePolicy::WriteBack,
+            write_allocate: true,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn sets(&self) -> u64 {
+        self.size / (self.line_size * self.associativity)
+    }
+}
+
+// Counted per cache line, an access that straddles two lines counts twice
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub read_hits: u64,
+    pub read_misses: u64,
+    pub write_hits: u64,
+    pub write_misses: u64,
+    pub evictions: u64,
+    // Dirty lines written back to memory on eviction
+    pub write_backs: u64,
+    // Writes that went straight to memory, from write through or no allocate
+    pub memory_writes: u64,
+}
+
+impl CacheStats {
+    pub fn accesses(&self) -> u64 {
+        self.read_hits + self.read_misses + self.write_hits + self.write_misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.accesses() == 0 {
+            0.0
+        } else {
+            (self.read_hits + self.write_hits) as f64 / self.accesses() as f64
+        }
+    }
+
+    pub fn miss_rate(&self) -> f64 {
+        if self.accesses() == 0 {
+            0.0
+        } else {
+            1.0 - self.hit_rate()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Line {
+    valid: bool,
+    dirty: bool,
+    tag: u64,
+    last_used: u64,
+    filled: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    config: CacheConfig,
+    sets: Vec<Vec<Line>>,
+    stats: CacheStats,
+    // Counts line accesses, used as the timestamp for LRU and FIFO
+    clock: u64,
+    rng_state: u64,
+}
+
+impl Cache {
+    pub fn new(config: CacheConfig) -> Self {
+        for (name, value) in [
+            ("size", config.size),
+            ("line size", config.line_size),
+            ("associativity", config.associativity),
+        ] {
+            if !value.is_power_of_two() {
+                panic!("cache {} must be a power of two, found {}", name, value)
+            }
+        }
+        if config.line_size * config.associativity > config.size {
+            panic!("cache is too small to hold a single set")
+        }
+
+        Self {
+            config,
+            sets: vec![
+                vec![Line::default(); config.associativity as usize];
+                config.sets() as usize
+            ],
+            stats: CacheStats::default(),
+            clock: 0,
+            rng_state: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    // Returns true if every line touched by the access was already cached
+    pub fn access(&mut self, adr: u64, size: u64, access: Access) -> bool {
+        let first_line = adr / self.config.line_size;
+        let last_line = adr.saturating_add(size.max(1) - 1) / self.config.line_size;
+
+        let mut hit = true;
+        for line_adr in first_line..=last_line {
+            hit &= self.access_line(line_adr, access);
+        }
+        hit
+    }
+
+    fn next_random(&mut self) -> u64 {
+        // xorshift64
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+        self.rng_state
+    }
+
+    fn victim(&mut self, set_index: usize) -> usize {
+        let set = &self.sets[set_index];
+        if let Some(way) = set.iter().position(|line| !line.valid) {
+            return way;
+        }
+
+        match self.config.replacement {
+            ReplacementPolicy::Lru => (0..set.len()).min_by_key(|way| set[*way].last_used),
+            ReplacementPolicy::Fifo => (0..set.len()).min_by_key(|way| set[*way].filled),
+            ReplacementPolicy::Random => {
+                let ways = set.len() as u64;
+                Some((self.next_random() % ways) as usize)
+            }
+        }
+        .unwrap()
+    }
+
+    fn access_line(&mut self, line_adr: u64, access: Access) -> bool {
+        self.clock += 1;
+
+        let set_index = (line_adr % self.config.sets()) as usize;
+        let tag = line_adr / self.config.sets();
+        let is_write = access == Access::Write;
+        let write_back = self.config.write_policy == WritePolicy::WriteBack;
+
+        let clock = self.clock;
+        if let Some(line) = self.sets[set_index]
+            .iter_mut()
+            .find(|line| line.valid && line.tag == tag)
+        {
+            line.last_used = clock;
+            if is_write {
+                self.stats.write_hits += 1;
+                if write_back {
+                    line.dirty = true;
+                } else {
+                    self.stats.memory_writes += 1;
+                }
+            } else {
+                self.stats.read_hits += 1;
+            }
+
+            return true;
+        }
+
+        if is_write {
+            self.stats.write_misses += 1;
+            if !self.config.write_allocate {
+                self.stats.memory_writes += 1;
+                return false;
+            }
+        } else {
+            self.stats.read_misses += 1;
+        }
+
+        let way = self.victim(set_index);
+        let line = &mut self.sets[set_index][way];
+        if line.valid {
+            self.stats.evictions += 1;
+            if line.dirty {
+                self.stats.write_backs += 1;
+            }
+        }
+
+        *line = Line {
+            valid: true,
+            dirty: is_write && write_back,
+            tag,
+            last_used: clock,
+            filled: clock,
+        };
+        if is_write && !write_back {
+            self.stats.memory_writes += 1;
+        }
+
+        false
+    }
+}
@@ -0,0 +1,160 @@
+use super::inst::*;
+use crate::asm::{self, AsmError, Word};
+
+fn parse_operand(word: &Word) -> Result<Operand, AsmError> {
+    if word.text.starts_with('%') {
+        Ok(Operand::Data(word.register()?))
+    } else if let Some(inner) = word
+        .text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+    {
+        let reg_word = Word {
+            text: inner,
+            line: word.line,
+            column: word.column + 1,
+        };
+        Ok(Operand::Adr(reg_word.register()?))
+    } else if word.text.starts_with('[') {
+        Err(word.error_at("unclosed address operand, expected `[%N]`"))
+    } else {
+        Ok(Operand::Imm(word.imm()?))
+    }
+}
+
+fn parse_target(word: &Word) -> Result<Target, AsmError> {
+    if word.text.starts_with('%') {
+        Ok(Target::Pointer(word.register()?))
+    } else {
+        Ok(Target::Label(word.label()?))
+    }
+}
+
+fn three_operand_inst(mnemonic: &str) -> Option<fn(Operand, Operand, Operand) -> Inst> {
+    Some(match mnemonic {
+        "shl" => Inst::Shl,
+        "shr" => Inst::Shr,
+        "and" => Inst::And,
+        "or" => Inst::Or,
+        "xor" => Inst::Xor,
+
+        "sadd" => Inst::SAdd,
+        "uadd" => Inst::UAdd,
+        "fadd" => Inst::FAdd,
+        "fadd32" => Inst::FAdd32,
+        "sub" => Inst::Sub,
+        "fsub" => Inst::FSub,
+        "fsub32" => Inst::FSub32,
+        "smul" => Inst::SMul,
+        "umul" => Inst::UMul,
+        "fmul" => Inst::FMul,
+        "fmul32" => Inst::FMul32,
+        "sdiv" => Inst::SDiv,
+        "udiv" => Inst::UDiv,
+        "fdiv" => Inst::FDiv,
+        "fdiv32" => Inst::FDiv32,
+        "srem" => Inst::SRem,
+        "urem" => Inst::URem,
+        "frem" => Inst::FRem,
+        "frem32" => Inst::FRem32,
+        "saddchecked" => Inst::SAddChecked,
+        "uaddchecked" => Inst::UAddChecked,
+        "ssubchecked" => Inst::SSubChecked,
+        "usubchecked" => Inst::USubChecked,
+        "smulchecked" => Inst::SMulChecked,
+        "umulchecked" => Inst::UMulChecked,
+        "sdivchecked" => Inst::SDivChecked,
+
+        "eq" => Inst::Eq,
+        "feq" => Inst::FEq,
+        "feq32" => Inst::FEq32,
+        "slt" => Inst::SLt,
+        "ult" => Inst::ULt,
+        "flt" => Inst::FLt,
+        "flt32" => Inst::FLt32,
+        "sgt" => Inst::SGt,
+        "ugt" => Inst::UGt,
+        "fgt" => Inst::FGt,
+        "fgt32" => Inst::FGt32,
+        _ => return None,
+    })
+}
+
+fn two_operand_inst(mnemonic: &str) -> Option<fn(Operand, Operand) -> Inst> {
+    Some(match mnemonic {
+        "move" => Inst::Move,
+        "move8" => Inst::Move8,
+        "move8s" => Inst::Move8S,
+        "move16" => Inst::Move16,
+        "move16s" => Inst::Move16S,
+        "move32" => Inst::Move32,
+        "move32s" => Inst::Move32S,
+
+        "not" => Inst::Not,
+
+        "stof" => Inst::SToF,
+        "utof" => Inst::UToF,
+        "ftos" => Inst::FToS,
+        "ftou" => Inst::FToU,
+        "ftosround" => Inst::FToSRound,
+        "ftouround" => Inst::FToURound,
+        "f32tof64" => Inst::F32ToF64,
+        "f64tof32" => Inst::F64ToF32,
+        _ => return None,
+    })
+}
+
+fn parse_inst(mnemonic: &Word, operands: &[Word]) -> Result<Inst, AsmError> {
+    if let Some(inst) = three_operand_inst(mnemonic.text) {
+        mnemonic.expect_operands(operands, 3)?;
+        return Ok(inst(
+            parse_operand(&operands[0])?,
+            parse_operand(&operands[1])?,
+            parse_operand(&operands[2])?,
+        ));
+    }
+
+    if let Some(inst) = two_operand_inst(mnemonic.text) {
+        mnemonic.expect_operands(operands, 2)?;
+        return Ok(inst(
+            parse_operand(&operands[0])?,
+            parse_operand(&operands[1])?,
+        ));
+    }
+
+    match mnemonic.text {
+        "syscall" => {
+            mnemonic.expect_operands(operands, 1)?;
+            Ok(Inst::SysCall(parse_operand(&operands[0])?))
+        }
+
+        "jump" => {
+            mnemonic.expect_operands(operands, 1)?;
+            Ok(Inst::Jump(parse_target(&operands[0])?))
+        }
+        "cjump" => {
+            mnemonic.expect_operands(operands, 2)?;
+            Ok(Inst::CJump(
+                parse_operand(&operands[0])?,
+                parse_target(&operands[1])?,
+            ))
+        }
+        "branch" => {
+            mnemonic.expect_operands(operands, 3)?;
+            Ok(Inst::Branch(
+                parse_operand(&operands[0])?,
+                parse_target(&operands[1])?,
+                parse_target(&operands[2])?,
+            ))
+        }
+
+        _ => Err(mnemonic.error_at(&format!("unknown instruction `{}`", mnemonic.text))),
+    }
+}
+
+pub fn parse(source: &str) -> Result<Vec<Block>, AsmError> {
+    Ok(asm::parse_blocks(source, parse_inst)?
+        .into_iter()
+        .map(|(label, insts)| Block { label, insts })
+        .collect())
+}
@@ -0,0 +1,456 @@
+use std::{
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+};
+
+use super::inst::*;
+use crate::{
+    encoding::{fits_signed, DecodeError, EncodeError},
+    shared::{Imm, Label, Register},
+};
+
+// Every instruction starts with a one byte opcode followed by its operands in
+// order. Each operand starts with a mode byte, the top 3 bits select the mode
+// and for register modes the low 5 bits hold the register ID. Immediates are
+// stored little-endian in the smallest width that sign extends back to the
+// original value, and label targets are absolute instruction indices (the
+// same unit `Rip` counts in).
+//
+//   data       | 000 | reg:5 |
+//   address    | 001 | reg:5 |
+//   imm8       | 010 | 00000 |  imm:8
+//   imm16      | 011 | 00000 |  imm:16
+//   imm32      | 100 | 00000 |  imm:32
+//   imm64      | 101 | 00000 |  imm:64
+//   label      | 110 | 00000 |  target:32
+//
+// Pointer targets use the data mode.
+
+const SYSCALL: u8 = 0x00;
+const MOVE: u8 = 0x01;
+const MOVE8: u8 = 0x02;
+const MOVE8S: u8 = 0x03;
+const MOVE16: u8 = 0x04;
+const MOVE16S: u8 = 0x05;
+const MOVE32: u8 = 0x06;
+const MOVE32S: u8 = 0x07;
+
+const JUMP: u8 = 0x08;
+const CJUMP: u8 = 0x09;
+const BRANCH: u8 = 0x0a;
+
+const SHL: u8 = 0x10;
+const SHR: u8 = 0x11;
+const AND: u8 = 0x12;
+const OR: u8 = 0x13;
+const XOR: u8 = 0x14;
+const NOT: u8 = 0x15;
+
+const SADD: u8 = 0x20;
+const UADD: u8 = 0x21;
+const FADD: u8 = 0x22;
+const SUB: u8 = 0x23;
+const FSUB: u8 = 0x24;
+const SMUL: u8 = 0x25;
+const UMUL: u8 = 0x26;
+const FMUL: u8 = 0x27;
+const SDIV: u8 = 0x28;
+const UDIV: u8 = 0x29;
+const FDIV: u8 = 0x2a;
+const SREM: u8 = 0x2b;
+const UREM: u8 = 0x2c;
+const FREM: u8 = 0x2d;
+
+const EQ: u8 = 0x30;
+const FEQ: u8 = 0x31;
+const SLT: u8 = 0x32;
+const ULT: u8 = 0x33;
+const FLT: u8 = 0x34;
+const SGT: u8 = 0x35;
+const UGT: u8 = 0x36;
+const FGT: u8 = 0x37;
+
+const FADD32: u8 = 0x60;
+const FSUB32: u8 = 0x61;
+const FMUL32: u8 = 0x62;
+const FDIV32: u8 = 0x63;
+const FREM32: u8 = 0x64;
+const FEQ32: u8 = 0x68;
+const FLT32: u8 = 0x69;
+const FGT32: u8 = 0x6a;
+
+const SADDCHECKED: u8 = 0x70;
+const UADDCHECKED: u8 = 0x71;
+const SSUBCHECKED: u8 = 0x72;
+const USUBCHECKED: u8 = 0x73;
+const SMULCHECKED: u8 = 0x74;
+const UMULCHECKED: u8 = 0x75;
+const SDIVCHECKED: u8 = 0x76;
+
+const STOF: u8 = 0x50;
+const UTOF: u8 = 0x51;
+const FTOS: u8 = 0x52;
+const FTOU: u8 = 0x53;
+const FTOSROUND: u8 = 0x54;
+const FTOUROUND: u8 = 0x55;
+const F32TOF64: u8 = 0x56;
+const F64TOF32: u8 = 0x57;
+
+const MODE_DATA: u8 = 0b000;
+const MODE_ADR: u8 = 0b001;
+const MODE_IMM8: u8 = 0b010;
+const MODE_IMM16: u8 = 0b011;
+const MODE_IMM32: u8 = 0b100;
+const MODE_IMM64: u8 = 0b101;
+const MODE_LABEL: u8 = 0b110;
+
+fn two_operand_inst(opcode: u8) -> Option<fn(Operand, Operand) -> Inst> {
+    Some(match opcode {
+        MOVE => Inst::Move,
+        MOVE8 => Inst::Move8,
+        MOVE8S => Inst::Move8S,
+        MOVE16 => Inst::Move16,
+        MOVE16S => Inst::Move16S,
+        MOVE32 => Inst::Move32,
+        MOVE32S => Inst::Move32S,
+
+        NOT => Inst::Not,
+
+        STOF => Inst::SToF,
+        UTOF => Inst::UToF,
+        FTOS => Inst::FToS,
+        FTOU => Inst::FToU,
+        FTOSROUND => Inst::FToSRound,
+        FTOUROUND => Inst::FToURound,
+        F32TOF64 => Inst::F32ToF64,
+        F64TOF32 => Inst::F64ToF32,
+        _ => return None,
+    })
+}
+
+fn three_operand_inst(opcode: u8) -> Option<fn(Operand, Operand, Operand) -> Inst> {
+    Some(match opcode {
+        SHL => Inst::Shl,
+        SHR => Inst::Shr,
+        AND => Inst::And,
+        OR => Inst::Or,
+        XOR => Inst::Xor,
+
+        SADD => Inst::SAdd,
+        UADD => Inst::UAdd,
+        FADD => Inst::FAdd,
+        FADD32 => Inst::FAdd32,
+        SUB => Inst::Sub,
+        FSUB => Inst::FSub,
+        FSUB32 => Inst::FSub32,
+        SMUL => Inst::SMul,
+        UMUL => Inst::UMul,
+        FMUL => Inst::FMul,
+        FMUL32 => Inst::FMul32,
+        SDIV => Inst::SDiv,
+        UDIV => Inst::UDiv,
+        FDIV => Inst::FDiv,
+        FDIV32 => Inst::FDiv32,
+        SREM => Inst::SRem,
+        UREM => Inst::URem,
+        FREM => Inst::FRem,
+        FREM32 => Inst::FRem32,
+        SADDCHECKED => Inst::SAddChecked,
+        UADDCHECKED => Inst::UAddChecked,
+        SSUBCHECKED => Inst::SSubChecked,
+        USUBCHECKED => Inst::USubChecked,
+        SMULCHECKED => Inst::SMulChecked,
+        UMULCHECKED => Inst::UMulChecked,
+        SDIVCHECKED => Inst::SDivChecked,
+
+        EQ => Inst::Eq,
+        FEQ => Inst::FEq,
+        FEQ32 => Inst::FEq32,
+        SLT => Inst::SLt,
+        ULT => Inst::ULt,
+        FLT => Inst::FLt,
+        FLT32 => Inst::FLt32,
+        SGT => Inst::SGt,
+        UGT => Inst::UGt,
+        FGT => Inst::FGt,
+        FGT32 => Inst::FGt32,
+        _ => return None,
+    })
+}
+
+fn mode_byte(mode: u8, reg: Option<&Register>) -> u8 {
+    mode << 5 | reg.map_or(0, |reg| reg.get_id())
+}
+
+fn encode_operand(operand: &Operand, bytes: &mut Vec<u8>) {
+    match operand {
+        Operand::Data(reg) => bytes.push(mode_byte(MODE_DATA, Some(reg))),
+        Operand::Adr(reg) => bytes.push(mode_byte(MODE_ADR, Some(reg))),
+        Operand::Imm(imm) => {
+            let value = imm.as_u64();
+            let (mode, width) = if fits_signed(value, 8) {
+                (MODE_IMM8, 1)
+            } else if fits_signed(value, 16) {
+                (MODE_IMM16, 2)
+            } else if fits_signed(value, 32) {
+                (MODE_IMM32, 4)
+            } else {
+                (MODE_IMM64, 8)
+            };
+
+            bytes.push(mode_byte(mode, None));
+            bytes.extend_from_slice(&value.to_le_bytes()[..width]);
+        }
+    }
+}
+
+fn operand_form(opcode: u8, operands: &[&Operand], bytes: &mut Vec<u8>) {
+    bytes.push(opcode);
+    for operand in operands {
+        encode_operand(operand, bytes);
+    }
+}
+
+fn encode_target(
+    target: &Target,
+    block_table: &HashMap<&str, usize>,
+    bytes: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+    match target {
+        Target::Label(label) => {
+            let inst_offset = if let Some(inst_offset) = block_table.get(label.0.as_str()) {
+                u32::try_from(*inst_offset)
+                    .map_err(|_| EncodeError::TargetOutOfRange(label.0.clone(), 32))?
+            } else {
+                return Err(EncodeError::UndefinedLabel(label.0.clone()));
+            };
+
+            bytes.push(mode_byte(MODE_LABEL, None));
+            bytes.extend_from_slice(&inst_offset.to_le_bytes());
+        }
+        Target::Pointer(reg) => bytes.push(mode_byte(MODE_DATA, Some(reg))),
+    }
+
+    Ok(())
+}
+
+fn encode_inst(
+    inst: &Inst,
+    block_table: &HashMap<&str, usize>,
+    bytes: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+    match inst {
+        Inst::Move(dst, src) => operand_form(MOVE, &[dst, src], bytes),
+        Inst::Move8(dst, src) => operand_form(MOVE8, &[dst, src], bytes),
+        Inst::Move8S(dst, src) => operand_form(MOVE8S, &[dst, src], bytes),
+        Inst::Move16(dst, src) => operand_form(MOVE16, &[dst, src], bytes),
+        Inst::Move16S(dst, src) => operand_form(MOVE16S, &[dst, src], bytes),
+        Inst::Move32(dst, src) => operand_form(MOVE32, &[dst, src], bytes),
+        Inst::Move32S(dst, src) => operand_form(MOVE32S, &[dst, src], bytes),
+
+        Inst::Not(dst, src) => operand_form(NOT, &[dst, src], bytes),
+
+        Inst::SToF(dst, src) => operand_form(STOF, &[dst, src], bytes),
+        Inst::UToF(dst, src) => operand_form(UTOF, &[dst, src], bytes),
+        Inst::FToS(dst, src) => operand_form(FTOS, &[dst, src], bytes),
+        Inst::FToU(dst, src) => operand_form(FTOU, &[dst, src], bytes),
+        Inst::FToSRound(dst, src) => operand_form(FTOSROUND, &[dst, src], bytes),
+        Inst::FToURound(dst, src) => operand_form(FTOUROUND, &[dst, src], bytes),
+        Inst::F32ToF64(dst, src) => operand_form(F32TOF64, &[dst, src], bytes),
+        Inst::F64ToF32(dst, src) => operand_form(F64TOF32, &[dst, src], bytes),
+
+        Inst::Shl(dst, lhs, rhs) => operand_form(SHL, &[dst, lhs, rhs], bytes),
+        Inst::Shr(dst, lhs, rhs) => operand_form(SHR, &[dst, lhs, rhs], bytes),
+        Inst::And(dst, lhs, rhs) => operand_form(AND, &[dst, lhs, rhs], bytes),
+        Inst::Or(dst, lhs, rhs) => operand_form(OR, &[dst, lhs, rhs], bytes),
+        Inst::Xor(dst, lhs, rhs) => operand_form(XOR, &[dst, lhs, rhs], bytes),
+
+        Inst::SAdd(dst, lhs, rhs) => operand_form(SADD, &[dst, lhs, rhs], bytes),
+        Inst::UAdd(dst, lhs, rhs) => operand_form(UADD, &[dst, lhs, rhs], bytes),
+        Inst::FAdd(dst, lhs, rhs) => operand_form(FADD, &[dst, lhs, rhs], bytes),
+        Inst::FAdd32(dst, lhs, rhs) => operand_form(FADD32, &[dst, lhs, rhs], bytes),
+        Inst::Sub(dst, lhs, rhs) => operand_form(SUB, &[dst, lhs, rhs], bytes),
+        Inst::FSub(dst, lhs, rhs) => operand_form(FSUB, &[dst, lhs, rhs], bytes),
+        Inst::FSub32(dst, lhs, rhs) => operand_form(FSUB32, &[dst, lhs, rhs], bytes),
+        Inst::SMul(dst, lhs, rhs) => operand_form(SMUL, &[dst, lhs, rhs], bytes),
+        Inst::UMul(dst, lhs, rhs) => operand_form(UMUL, &[dst, lhs, rhs], bytes),
+        Inst::FMul(dst, lhs, rhs) => operand_form(FMUL, &[dst, lhs, rhs], bytes),
+        Inst::FMul32(dst, lhs, rhs) => operand_form(FMUL32, &[dst, lhs, rhs], bytes),
+        Inst::SDiv(dst, lhs, rhs) => operand_form(SDIV, &[dst, lhs, rhs], bytes),
+        Inst::UDiv(dst, lhs, rhs) => operand_form(UDIV, &[dst, lhs, rhs], bytes),
+        Inst::FDiv(dst, lhs, rhs) => operand_form(FDIV, &[dst, lhs, rhs], bytes),
+        Inst::FDiv32(dst, lhs, rhs) => operand_form(FDIV32, &[dst, lhs, rhs], bytes),
+        Inst::SRem(dst, lhs, rhs) => operand_form(SREM, &[dst, lhs, rhs], bytes),
+        Inst::URem(dst, lhs, rhs) => operand_form(UREM, &[dst, lhs, rhs], bytes),
+        Inst::FRem(dst, lhs, rhs) => operand_form(FREM, &[dst, lhs, rhs], bytes),
+        Inst::FRem32(dst, lhs, rhs) => operand_form(FREM32, &[dst, lhs, rhs], bytes),
+        Inst::SAddChecked(dst, lhs, rhs) => operand_form(SADDCHECKED, &[dst, lhs, rhs], bytes),
+        Inst::UAddChecked(dst, lhs, rhs) => operand_form(UADDCHECKED, &[dst, lhs, rhs], bytes),
+        Inst::SSubChecked(dst, lhs, rhs) => operand_form(SSUBCHECKED, &[dst, lhs, rhs], bytes),
+        Inst::USubChecked(dst, lhs, rhs) => operand_form(USUBCHECKED, &[dst, lhs, rhs], bytes),
+        Inst::SMulChecked(dst, lhs, rhs) => operand_form(SMULCHECKED, &[dst, lhs, rhs], bytes),
+        Inst::UMulChecked(dst, lhs, rhs) => operand_form(UMULCHECKED, &[dst, lhs, rhs], bytes),
+        Inst::SDivChecked(dst, lhs, rhs) => operand_form(SDIVCHECKED, &[dst, lhs, rhs], bytes),
+
+        Inst::Eq(dst, lhs, rhs) => operand_form(EQ, &[dst, lhs, rhs], bytes),
+        Inst::FEq(dst, lhs, rhs) => operand_form(FEQ, &[dst, lhs, rhs], bytes),
+        Inst::FEq32(dst, lhs, rhs) => operand_form(FEQ32, &[dst, lhs, rhs], bytes),
+        Inst::SLt(dst, lhs, rhs) => operand_form(SLT, &[dst, lhs, rhs], bytes),
+        Inst::ULt(dst, lhs, rhs) => operand_form(ULT, &[dst, lhs, rhs], bytes),
+        Inst::FLt(dst, lhs, rhs) => operand_form(FLT, &[dst, lhs, rhs], bytes),
+        Inst::FLt32(dst, lhs, rhs) => operand_form(FLT32, &[dst, lhs, rhs], bytes),
+        Inst::SGt(dst, lhs, rhs) => operand_form(SGT, &[dst, lhs, rhs], bytes),
+        Inst::UGt(dst, lhs, rhs) => operand_form(UGT, &[dst, lhs, rhs], bytes),
+        Inst::FGt(dst, lhs, rhs) => operand_form(FGT, &[dst, lhs, rhs], bytes),
+        Inst::FGt32(dst, lhs, rhs) => operand_form(FGT32, &[dst, lhs, rhs], bytes),
+
+        Inst::SysCall(operand) => {
+            bytes.push(SYSCALL);
+            encode_operand(operand, bytes);
+        }
+
+        Inst::Jump(target) => {
+            bytes.push(JUMP);
+            encode_target(target, block_table, bytes)?;
+        }
+        Inst::CJump(cond, target) => {
+            bytes.push(CJUMP);
+            encode_operand(cond, bytes);
+            encode_target(target, block_table, bytes)?;
+        }
+        Inst::Branch(cond, true_target, false_target) => {
+            bytes.push(BRANCH);
+            encode_operand(cond, bytes);
+            encode_target(true_target, block_table, bytes)?;
+            encode_target(false_target, block_table, bytes)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn encode(blocks: &[Block]) -> Result<Vec<u8>, EncodeError> {
+    let mut block_table = HashMap::new();
+    let mut inst_count = 0;
+    for block in blocks {
+        block_table.insert(block.label.as_str(), inst_count);
+        inst_count += block.insts.len();
+    }
+
+    let mut bytes = Vec::new();
+    for inst in blocks.iter().flat_map(|block| &block.insts) {
+        encode_inst(inst, &block_table, &mut bytes)?;
+    }
+
+    Ok(bytes)
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    current: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn next_bytes(&mut self, count: usize) -> Result<&'a [u8], DecodeError> {
+        if let Some(bytes) = self.bytes.get(self.current..self.current + count) {
+            self.current += count;
+            Ok(bytes)
+        } else {
+            Err(DecodeError::UnexpectedEnd(self.current))
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.next_bytes(1)?[0])
+    }
+
+    // Called right after reading the mode byte, so it sits just behind `current`
+    fn decode_reg(&self, mode_byte: u8) -> Result<Register, DecodeError> {
+        let id = mode_byte & 0b11111;
+        if id > Register::Rip.get_id() {
+            return Err(DecodeError::InvalidRegister(self.current - 1, id));
+        }
+
+        Ok(Register::from_id(id))
+    }
+
+    fn decode_imm(&mut self, width: usize) -> Result<Imm, DecodeError> {
+        let mut value = [0; 8];
+        value[..width].copy_from_slice(self.next_bytes(width)?);
+        let value = u64::from_le_bytes(value);
+
+        let shift = 64 - 8 * width as u32;
+        Ok(Imm::Int((((value << shift) as i64) >> shift) as u64))
+    }
+
+    fn decode_operand(&mut self) -> Result<Operand, DecodeError> {
+        let mode_byte = self.next_byte()?;
+        Ok(match mode_byte >> 5 {
+            MODE_DATA => Operand::Data(self.decode_reg(mode_byte)?),
+            MODE_ADR => Operand::Adr(self.decode_reg(mode_byte)?),
+            MODE_IMM8 => Operand::Imm(self.decode_imm(1)?),
+            MODE_IMM16 => Operand::Imm(self.decode_imm(2)?),
+            MODE_IMM32 => Operand::Imm(self.decode_imm(4)?),
+            MODE_IMM64 => Operand::Imm(self.decode_imm(8)?),
+            mode => return Err(DecodeError::InvalidMode(self.current - 1, mode)),
+        })
+    }
+
+    fn decode_target(&mut self) -> Result<Target, DecodeError> {
+        let mode_byte = self.next_byte()?;
+        Ok(match mode_byte >> 5 {
+            MODE_DATA => Target::Pointer(self.decode_reg(mode_byte)?),
+            MODE_LABEL => {
+                let target = u32::from_le_bytes(self.next_bytes(4)?.try_into().unwrap());
+                Target::Label(Label(format!("L{}", target)))
+            }
+            mode => return Err(DecodeError::InvalidMode(self.current - 1, mode)),
+        })
+    }
+
+    fn decode_inst(&mut self) -> Result<Inst, DecodeError> {
+        let opcode = self.next_byte()?;
+
+        if let Some(inst) = three_operand_inst(opcode) {
+            let dst = self.decode_operand()?;
+            let lhs = self.decode_operand()?;
+            let rhs = self.decode_operand()?;
+            return Ok(inst(dst, lhs, rhs));
+        }
+
+        if let Some(inst) = two_operand_inst(opcode) {
+            let dst = self.decode_operand()?;
+            return Ok(inst(dst, self.decode_operand()?));
+        }
+
+        Ok(match opcode {
+            SYSCALL => Inst::SysCall(self.decode_operand()?),
+
+            JUMP => Inst::Jump(self.decode_target()?),
+            CJUMP => {
+                let cond = self.decode_operand()?;
+                Inst::CJump(cond, self.decode_target()?)
+            }
+            BRANCH => {
+                let cond = self.decode_operand()?;
+                let true_target = self.decode_target()?;
+                Inst::Branch(cond, true_target, self.decode_target()?)
+            }
+
+            _ => return Err(DecodeError::UnknownOpcode(self.current - 1, opcode)),
+        })
+    }
+}
+
+// Decoded label targets are named after the instruction index they point at,
+// e.g. `@L12`. Immediates come back as `Imm::Int` holding the original bits.
+pub fn decode(bytes: &[u8]) -> Result<Vec<Inst>, DecodeError> {
+    let mut decoder = Decoder { bytes, current: 0 };
+
+    let mut insts = Vec::new();
+    while decoder.current < bytes.len() {
+        insts.push(decoder.decode_inst()?);
+    }
+
+    Ok(insts)
+}
@@ -1,4 +1,7 @@
-use crate::shared::{Imm, Label, Register};
+use crate::{
+    cost::InstClass,
+    shared::{Imm, Label, Register},
+};
 
 #[derive(Debug, Clone)]
 pub struct Block {
@@ -55,13 +58,21 @@ pub enum Inst {
 
     // Memory & registers
     Move(Operand, Operand),
+    // Sub-word moves, memory operands are accessed with the given width and
+    // the value is zero (`MoveN`) or sign (`MoveNS`) extended into registers
+    Move8(Operand, Operand),
+    Move8S(Operand, Operand),
+    Move16(Operand, Operand),
+    Move16S(Operand, Operand),
+    Move32(Operand, Operand),
+    Move32S(Operand, Operand),
 
     // Control flow
     Jump(Target),
     CJump(Operand, Target),
     Branch(Operand, Target, Target),
 
-    // Bitwise operations
+    // Bitwise operations, shift amounts are taken modulo 64
     Shl(Operand, Operand, Operand),
     Shr(Operand, Operand, Operand),
     And(Operand, Operand, Operand),
@@ -69,45 +80,151 @@ pub enum Inst {
     Xor(Operand, Operand, Operand),
     Not(Operand, Operand),
 
-    // Arithmetic operations
+    // Arithmetic operations, integer ones wrap around on overflow
     SAdd(Operand, Operand, Operand),
     UAdd(Operand, Operand, Operand),
     FAdd(Operand, Operand, Operand),
+    // The `32` variants of the float instructions work on f32 values held in
+    // the low 32 bits of a register
+    FAdd32(Operand, Operand, Operand),
 
     Sub(Operand, Operand, Operand),
     FSub(Operand, Operand, Operand),
+    FSub32(Operand, Operand, Operand),
 
     SMul(Operand, Operand, Operand),
     UMul(Operand, Operand, Operand),
     FMul(Operand, Operand, Operand),
+    FMul32(Operand, Operand, Operand),
 
     SDiv(Operand, Operand, Operand),
     UDiv(Operand, Operand, Operand),
     FDiv(Operand, Operand, Operand),
+    FDiv32(Operand, Operand, Operand),
 
     SRem(Operand, Operand, Operand),
     URem(Operand, Operand, Operand),
     FRem(Operand, Operand, Operand),
+    FRem32(Operand, Operand, Operand),
+
+    // Like the wrapping instructions but trap on overflow
+    SAddChecked(Operand, Operand, Operand),
+    UAddChecked(Operand, Operand, Operand),
+    SSubChecked(Operand, Operand, Operand),
+    USubChecked(Operand, Operand, Operand),
+    SMulChecked(Operand, Operand, Operand),
+    UMulChecked(Operand, Operand, Operand),
+    SDivChecked(Operand, Operand, Operand),
+
+    // Conversions, `SToF`/`UToF` turn a signed or unsigned integer into an f64
+    // and `FToS`/`FToU` go back by truncating, or by rounding half away from
+    // zero for the `Round` variants. Out of range floats saturate and NaN
+    // becomes 0. f32 values live in the low 32 bits of a register.
+    SToF(Operand, Operand),
+    UToF(Operand, Operand),
+    FToS(Operand, Operand),
+    FToU(Operand, Operand),
+    FToSRound(Operand, Operand),
+    FToURound(Operand, Operand),
+    F32ToF64(Operand, Operand),
+    F64ToF32(Operand, Operand),
 
     // Comparative operators
     Eq(Operand, Operand, Operand),
     FEq(Operand, Operand, Operand),
+    FEq32(Operand, Operand, Operand),
 
     SLt(Operand, Operand, Operand),
     ULt(Operand, Operand, Operand),
     FLt(Operand, Operand, Operand),
+    FLt32(Operand, Operand, Operand),
 
     SGt(Operand, Operand, Operand),
     UGt(Operand, Operand, Operand),
     FGt(Operand, Operand, Operand),
+    FGt32(Operand, Operand, Operand),
 }
 
 impl Inst {
+    pub fn class(&self) -> InstClass {
+        match self {
+            Self::SysCall(_) => InstClass::SysCall,
+
+            // Memory operands are charged separately through the cost model
+            Self::Move(_, _)
+            | Self::Move8(_, _)
+            | Self::Move8S(_, _)
+            | Self::Move16(_, _)
+            | Self::Move16S(_, _)
+            | Self::Move32(_, _)
+            | Self::Move32S(_, _) => InstClass::Move,
+
+            Self::Jump(_) => InstClass::Jump,
+            Self::CJump(_, _) | Self::Branch(_, _, _) => InstClass::Branch,
+
+            Self::Shl(_, _, _)
+            | Self::Shr(_, _, _)
+            | Self::And(_, _, _)
+            | Self::Or(_, _, _)
+            | Self::Xor(_, _, _)
+            | Self::Not(_, _)
+            | Self::SAdd(_, _, _)
+            | Self::UAdd(_, _, _)
+            | Self::Sub(_, _, _) => InstClass::IntAlu,
+            Self::SMul(_, _, _) | Self::UMul(_, _, _) => InstClass::IntMul,
+            Self::SDiv(_, _, _)
+            | Self::UDiv(_, _, _)
+            | Self::SRem(_, _, _)
+            | Self::URem(_, _, _) => InstClass::IntDiv,
+            Self::SAddChecked(_, _, _)
+            | Self::UAddChecked(_, _, _)
+            | Self::SSubChecked(_, _, _)
+            | Self::USubChecked(_, _, _) => InstClass::IntAlu,
+            Self::SMulChecked(_, _, _) | Self::UMulChecked(_, _, _) => InstClass::IntMul,
+            Self::SDivChecked(_, _, _) => InstClass::IntDiv,
+            Self::FAdd(_, _, _)
+            | Self::FAdd32(_, _, _)
+            | Self::FSub(_, _, _)
+            | Self::FSub32(_, _, _) => InstClass::FloatAdd,
+            Self::FMul(_, _, _) | Self::FMul32(_, _, _) => InstClass::FloatMul,
+            Self::FDiv(_, _, _)
+            | Self::FDiv32(_, _, _)
+            | Self::FRem(_, _, _)
+            | Self::FRem32(_, _, _) => InstClass::FloatDiv,
+            Self::SToF(_, _)
+            | Self::UToF(_, _)
+            | Self::FToS(_, _)
+            | Self::FToU(_, _)
+            | Self::FToSRound(_, _)
+            | Self::FToURound(_, _)
+            | Self::F32ToF64(_, _)
+            | Self::F64ToF32(_, _) => InstClass::Convert,
+
+            Self::Eq(_, _, _)
+            | Self::FEq(_, _, _)
+            | Self::FEq32(_, _, _)
+            | Self::SLt(_, _, _)
+            | Self::ULt(_, _, _)
+            | Self::FLt(_, _, _)
+            | Self::FLt32(_, _, _)
+            | Self::SGt(_, _, _)
+            | Self::UGt(_, _, _)
+            | Self::FGt(_, _, _)
+            | Self::FGt32(_, _, _) => InstClass::Compare,
+        }
+    }
+
     pub fn as_asm(&self) -> String {
         match self {
             Self::SysCall(operand) => format!("syscall {}", operand.as_asm()),
 
             Self::Move(dst, src) => format!("move {} {}", dst.as_asm(), src.as_asm()),
+            Self::Move8(dst, src) => format!("move8 {} {}", dst.as_asm(), src.as_asm()),
+            Self::Move8S(dst, src) => format!("move8s {} {}", dst.as_asm(), src.as_asm()),
+            Self::Move16(dst, src) => format!("move16 {} {}", dst.as_asm(), src.as_asm()),
+            Self::Move16S(dst, src) => format!("move16s {} {}", dst.as_asm(), src.as_asm()),
+            Self::Move32(dst, src) => format!("move32 {} {}", dst.as_asm(), src.as_asm()),
+            Self::Move32S(dst, src) => format!("move32s {} {}", dst.as_asm(), src.as_asm()),
 
             Self::Jump(target) => format!("jump {}", target.as_asm()),
             Self::CJump(cond, if_target) => {
@@ -145,6 +262,9 @@ impl Inst {
             Self::FAdd(dst, lhs, rhs) => {
                 format!("fadd {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
+            Self::FAdd32(dst, lhs, rhs) => {
+                format!("fadd32 {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
+            }
 
             Self::Sub(dst, lhs, rhs) => {
                 format!("sub {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
@@ -152,6 +272,9 @@ impl Inst {
             Self::FSub(dst, lhs, rhs) => {
                 format!("fsub {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
+            Self::FSub32(dst, lhs, rhs) => {
+                format!("fsub32 {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
+            }
 
             Self::SMul(dst, lhs, rhs) => {
                 format!("smul {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
@@ -162,6 +285,9 @@ impl Inst {
             Self::FMul(dst, lhs, rhs) => {
                 format!("fmul {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
+            Self::FMul32(dst, lhs, rhs) => {
+                format!("fmul32 {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
+            }
 
             Self::SDiv(dst, lhs, rhs) => {
                 format!("sdiv {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
@@ -172,6 +298,9 @@ impl Inst {
             Self::FDiv(dst, lhs, rhs) => {
                 format!("fdiv {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
+            Self::FDiv32(dst, lhs, rhs) => {
+                format!("fdiv32 {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
+            }
 
             Self::SRem(dst, lhs, rhs) => {
                 format!("srem {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
@@ -182,6 +311,75 @@ impl Inst {
             Self::FRem(dst, lhs, rhs) => {
                 format!("frem {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
+            Self::FRem32(dst, lhs, rhs) => {
+                format!("frem32 {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
+            }
+
+            Self::SAddChecked(dst, lhs, rhs) => {
+                format!(
+                    "saddchecked {} {} {}",
+                    dst.as_asm(),
+                    lhs.as_asm(),
+                    rhs.as_asm()
+                )
+            }
+            Self::UAddChecked(dst, lhs, rhs) => {
+                format!(
+                    "uaddchecked {} {} {}",
+                    dst.as_asm(),
+                    lhs.as_asm(),
+                    rhs.as_asm()
+                )
+            }
+            Self::SSubChecked(dst, lhs, rhs) => {
+                format!(
+                    "ssubchecked {} {} {}",
+                    dst.as_asm(),
+                    lhs.as_asm(),
+                    rhs.as_asm()
+                )
+            }
+            Self::USubChecked(dst, lhs, rhs) => {
+                format!(
+                    "usubchecked {} {} {}",
+                    dst.as_asm(),
+                    lhs.as_asm(),
+                    rhs.as_asm()
+                )
+            }
+            Self::SMulChecked(dst, lhs, rhs) => {
+                format!(
+                    "smulchecked {} {} {}",
+                    dst.as_asm(),
+                    lhs.as_asm(),
+                    rhs.as_asm()
+                )
+            }
+            Self::UMulChecked(dst, lhs, rhs) => {
+                format!(
+                    "umulchecked {} {} {}",
+                    dst.as_asm(),
+                    lhs.as_asm(),
+                    rhs.as_asm()
+                )
+            }
+            Self::SDivChecked(dst, lhs, rhs) => {
+                format!(
+                    "sdivchecked {} {} {}",
+                    dst.as_asm(),
+                    lhs.as_asm(),
+                    rhs.as_asm()
+                )
+            }
+
+            Self::SToF(dst, src) => format!("stof {} {}", dst.as_asm(), src.as_asm()),
+            Self::UToF(dst, src) => format!("utof {} {}", dst.as_asm(), src.as_asm()),
+            Self::FToS(dst, src) => format!("ftos {} {}", dst.as_asm(), src.as_asm()),
+            Self::FToU(dst, src) => format!("ftou {} {}", dst.as_asm(), src.as_asm()),
+            Self::FToSRound(dst, src) => format!("ftosround {} {}", dst.as_asm(), src.as_asm()),
+            Self::FToURound(dst, src) => format!("ftouround {} {}", dst.as_asm(), src.as_asm()),
+            Self::F32ToF64(dst, src) => format!("f32tof64 {} {}", dst.as_asm(), src.as_asm()),
+            Self::F64ToF32(dst, src) => format!("f64tof32 {} {}", dst.as_asm(), src.as_asm()),
 
             Self::Eq(dst, lhs, rhs) => {
                 format!("eq {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
@@ -189,6 +387,9 @@ impl Inst {
             Self::FEq(dst, lhs, rhs) => {
                 format!("feq {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
+            Self::FEq32(dst, lhs, rhs) => {
+                format!("feq32 {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
+            }
             Self::SLt(dst, lhs, rhs) => {
                 format!("slt {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
@@ -198,6 +399,9 @@ impl Inst {
             Self::FLt(dst, lhs, rhs) => {
                 format!("flt {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
+            Self::FLt32(dst, lhs, rhs) => {
+                format!("flt32 {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
+            }
             Self::SGt(dst, lhs, rhs) => {
                 format!("sgt {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
@@ -207,6 +411,9 @@ impl Inst {
             Self::FGt(dst, lhs, rhs) => {
                 format!("fgt {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
             }
+            Self::FGt32(dst, lhs, rhs) => {
+                format!("fgt32 {} {} {}", dst.as_asm(), lhs.as_asm(), rhs.as_asm())
+            }
         }
     }
 }
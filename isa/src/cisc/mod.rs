@@ -1,2 +1,4 @@
+pub mod asm;
+pub mod encoding;
 pub mod inst;
 pub mod vm;
@@ -1,20 +1,72 @@
-use std::{collections::HashMap, convert::TryFrom, io::Write, thread, time::Duration};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    io::{BufReader, Read, Write},
+    ops::Range,
+    rc::Rc,
+};
 
 use super::inst::*;
-use crate::shared::{Imm, Register, Registers};
+use crate::{
+    cache::Cache,
+    clock::Clock,
+    cost::{CostModel, DefaultCostModel, InstClass, PerfCounters},
+    device::SharedDevice,
+    fs::VirtualFs,
+    memory::Memory,
+    predictor::{BranchStats, SharedPredictor},
+    shared::{
+        sign_extend, zero_extend, Access, ExitStatus, HaltReason, Imm, Location, MemoryAccess,
+        Register, Registers, Stop, TrapReason, UndoEntry, VmError, WatchKind, Watchpoint,
+        WatchpointHit,
+    },
+    syscall::{SyscallContext, SyscallHandler, Syscalls},
+    trace::Tracer,
+};
 
 #[derive(Debug, Clone)]
-pub struct VM<'a, W: Write, const MEMORY_SIZE: usize> {
+pub struct VM<'a, R: Read, W: Write> {
     registers: Registers,
-    memory: [u8; MEMORY_SIZE],
+    memory: Memory,
     writer: W,
+    reader: Rc<RefCell<BufReader<R>>>,
 
     insts: Vec<Inst>,
     block_table: HashMap<&'a str, usize>,
+
+    cost_model: Rc<dyn CostModel + 'a>,
+    perf: PerfCounters,
+    cache: Option<Cache>,
+    devices: Vec<(Range<u64>, SharedDevice<'a>)>,
+    predictor: Option<SharedPredictor<'a>>,
+    syscalls: Syscalls<'a>,
+    clock: Clock,
+    fs: VirtualFs,
+    // Set once the program called `exit`
+    exit_code: Option<i64>,
+    branch_stats: BranchStats,
+    // Direction of the conditional branch being executed, if it is one
+    branch_outcome: Option<bool>,
+
+    breakpoints: HashSet<u64>,
+    // Set when `run_until_breakpoint` stopped at a breakpoint, so resuming
+    // executes that instruction instead of stopping at it again
+    resuming: bool,
+    watchpoints: Vec<Watchpoint>,
+    // The first watchpoint triggered by the instruction being executed
+    watchpoint_hit: Option<WatchpointHit>,
+
+    tracer: Option<Tracer<'a>>,
+    // `None` unless history recording was turned on with `record_history`
+    undo_log: Option<Vec<UndoEntry>>,
+    // Memory touched by the instruction being executed, only kept while tracing
+    // or recording history
+    memory_accesses: Vec<MemoryAccess>,
 }
 
-impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
-    pub fn new(blocks: &'a [Block], writer: W) -> Self {
+impl<'a, R: Read, W: Write> VM<'a, R, W> {
+    pub fn new(blocks: &'a [Block], reader: R, writer: W, memory: Memory) -> Self {
         let mut block_table = HashMap::new();
         let mut insts = Vec::new();
 
@@ -29,282 +81,789 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
             }
         }
 
+        // Execution starts at `main`, a program without one halts immediately
+        let mut registers = Registers::new();
+        let entry = block_table
+            .get("main")
+            .map_or(u64::MAX, |main_routine_begin| *main_routine_begin as u64);
+        registers.set(&Register::Rip, entry);
+
         Self {
-            registers: Registers::new(),
-            memory: [0; MEMORY_SIZE],
+            registers,
+            memory,
             writer,
+            reader: Rc::new(RefCell::new(BufReader::new(reader))),
 
             insts,
             block_table,
+
+            cost_model: Rc::new(DefaultCostModel),
+            perf: PerfCounters::default(),
+            cache: None,
+            devices: Vec::new(),
+            predictor: None,
+            syscalls: Syscalls::default(),
+            clock: Clock::default(),
+            fs: VirtualFs::default(),
+            exit_code: None,
+            branch_stats: BranchStats::default(),
+            branch_outcome: None,
+
+            breakpoints: HashSet::new(),
+            resuming: false,
+            watchpoints: Vec::new(),
+            watchpoint_hit: None,
+
+            tracer: None,
+            undo_log: None,
+            memory_accesses: Vec::new(),
+        }
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn rip(&self) -> u64 {
+        self.registers.get(&Register::Rip)
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    // Writes made through here bypass watchpoints, tracing and the undo log
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    pub fn current_inst(&self) -> Option<&Inst> {
+        usize::try_from(self.rip())
+            .ok()
+            .and_then(|rip| self.insts.get(rip))
+    }
+
+    pub fn perf(&self) -> PerfCounters {
+        self.perf
+    }
+
+    pub fn set_cost_model(&mut self, cost_model: impl CostModel + 'a) {
+        self.cost_model = Rc::new(cost_model);
+    }
+
+    // Every memory access goes through the attached cache model, which only
+    // keeps statistics and never changes what the program sees
+    pub fn set_cache(&mut self, cache: Option<Cache>) {
+        self.cache = cache;
+    }
+
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    // Handles `syscall` with `id` from now on, replacing the standard syscall
+    // or a previously registered handler with the same ID
+    pub fn register_syscall(&mut self, id: u64, handler: impl SyscallHandler + 'a) {
+        self.syscalls.register(id, handler);
+    }
+
+    pub fn syscalls_mut(&mut self) -> &mut Syscalls<'a> {
+        &mut self.syscalls
+    }
+
+    // Virtual by default, so `sleep` never blocks and runs are deterministic
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    // The files programs can access through the file syscalls, empty unless
+    // preloaded
+    pub fn set_fs(&mut self, fs: VirtualFs) {
+        self.fs = fs;
+    }
+
+    pub fn fs(&self) -> &VirtualFs {
+        &self.fs
+    }
+
+    pub fn fs_mut(&mut self) -> &mut VirtualFs {
+        &mut self.fs
+    }
+
+    // Replacing the predictor also clears the branch statistics
+    pub fn set_branch_predictor(&mut self, predictor: Option<SharedPredictor<'a>>) {
+        self.predictor = predictor;
+        self.branch_stats = BranchStats::default();
+    }
+
+    pub fn branch_stats(&self) -> Option<&BranchStats> {
+        self.predictor.as_ref().map(|_| &self.branch_stats)
+    }
+
+    // Loads and stores inside `range` go to the device instead of memory. They
+    // bypass the cache and aren't rewound by `step_back`
+    pub fn map_device(&mut self, range: Range<u64>, device: SharedDevice<'a>) {
+        if let Some((mapped, _)) = self
+            .devices
+            .iter()
+            .find(|(mapped, _)| mapped.start < range.end && range.start < mapped.end)
+        {
+            panic!(
+                "device range {:?} overlaps already mapped range {:?}",
+                range, mapped
+            )
         }
+
+        self.devices.push((range, device));
     }
 
-    fn resolve_operand(&self, operand: &Operand) -> u64 {
+    pub fn is_halted(&self) -> bool {
+        self.rip() == u64::MAX
+    }
+
+    // Returns the instruction index the breakpoint was placed at, or `None` if
+    // the label doesn't exist
+    pub fn add_breakpoint(&mut self, location: impl Into<Location>) -> Option<u64> {
+        let index = self.resolve_location(location.into())?;
+        self.breakpoints.insert(index);
+        Some(index)
+    }
+
+    pub fn remove_breakpoint(&mut self, location: impl Into<Location>) -> bool {
+        match self.resolve_location(location.into()) {
+            Some(index) => self.breakpoints.remove(&index),
+            None => false,
+        }
+    }
+
+    pub fn add_watchpoint(&mut self, range: Range<u64>, kind: WatchKind) {
+        self.watchpoints.push(Watchpoint { range, kind });
+    }
+
+    pub fn remove_watchpoint(&mut self, range: &Range<u64>) -> bool {
+        let watchpoint_count = self.watchpoints.len();
+        self.watchpoints
+            .retain(|watchpoint| watchpoint.range != *range);
+        self.watchpoints.len() != watchpoint_count
+    }
+
+    // Tracing is off until a tracer is set, passing `None` turns it back off
+    pub fn set_tracer(&mut self, tracer: Option<Tracer<'a>>) {
+        self.tracer = tracer;
+    }
+
+    // Keeps an undo log of every executed instruction so `step_back` can rewind
+    // registers and memory. Output already written, syscall side effects, cache,
+    // branch predictor and clock state are not undone. Turning it off drops the
+    // recorded history
+    pub fn record_history(&mut self, enabled: bool) {
+        if !enabled {
+            self.undo_log = None;
+        } else if self.undo_log.is_none() {
+            self.undo_log = Some(Vec::new());
+        }
+    }
+
+    // Undoes the last recorded instruction, returns false once there's no
+    // history left
+    pub fn step_back(&mut self) -> bool {
+        let entry = match self.undo_log.as_mut().and_then(|undo_log| undo_log.pop()) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        for (adr, size, old_value) in entry.memory_writes.iter().rev() {
+            if self.is_mapped(*adr) {
+                continue;
+            }
+
+            self.memory
+                .write_uint(*adr, *size, *old_value)
+                .expect("undone writes were in bounds when they happened");
+        }
+        self.registers = entry.registers;
+        self.perf = entry.perf;
+        self.resuming = false;
+        self.exit_code = None;
+
+        true
+    }
+
+    // Steps back until `Rip` is at a breakpoint, returning its index, or `None`
+    // when the start of the recorded history is reached first
+    pub fn run_back_to_breakpoint(&mut self) -> Option<u64> {
+        while self.step_back() {
+            let rip = self.rip();
+            if self.breakpoints.contains(&rip) {
+                // Running forward again executes the instruction instead of
+                // stopping at the same breakpoint
+                self.resuming = true;
+                return Some(rip);
+            }
+        }
+
+        None
+    }
+
+    fn record_access(
+        &mut self,
+        adr: u64,
+        size: u64,
+        access: Access,
+        old_value: u64,
+        new_value: u64,
+    ) {
+        match access {
+            Access::Read => self.perf.loads += 1,
+            Access::Write => self.perf.stores += 1,
+        }
+        self.perf.cycles += self.cost_model.memory_access_cost();
+        let is_mapped = self.is_mapped(adr);
+        if let Some(cache) = self.cache.as_mut().filter(|_| !is_mapped) {
+            if !cache.access(adr, size, access) {
+                self.perf.cycles += self.cost_model.cache_miss_cost();
+            }
+        }
+
+        if self.tracer.is_some() || self.undo_log.is_some() {
+            self.memory_accesses.push(MemoryAccess {
+                adr,
+                size,
+                access,
+                old_value,
+                new_value,
+            });
+        }
+
+        if self.watchpoint_hit.is_some() {
+            return;
+        }
+
+        if self
+            .watchpoints
+            .iter()
+            .any(|watchpoint| watchpoint.triggers(adr, size, access))
+        {
+            self.watchpoint_hit = Some(WatchpointHit {
+                inst_index: self.rip(),
+                adr,
+                size,
+                access,
+                old_value,
+                new_value,
+            });
+        }
+    }
+
+    fn resolve_location(&self, location: Location) -> Option<u64> {
+        match location {
+            Location::Label(label) => self
+                .block_table
+                .get(label.as_str())
+                .map(|inst_offset| *inst_offset as u64),
+            Location::Index(index) => Some(index),
+        }
+    }
+
+    fn trap(&self, reason: TrapReason) -> VmError {
+        VmError {
+            inst_index: self.registers.get(&Register::Rip),
+            registers: Box::new(self.registers),
+            reason,
+        }
+    }
+
+    // Finds the device mapped at `adr`. An access has to fit entirely within
+    // the device's range, one that only partly overlaps it traps, whichever end
+    // it sticks out of
+    fn device_at(&self, adr: u64, width: u64) -> Result<Option<(u64, SharedDevice<'a>)>, VmError> {
+        let end = adr.saturating_add(width);
+        for (range, device) in &self.devices {
+            if adr < range.end && range.start < end {
+                if adr < range.start || end > range.end {
+                    return Err(self.trap(TrapReason::MemoryOutOfBounds(adr)));
+                }
+
+                return Ok(Some((adr - range.start, Rc::clone(device))));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Only called for accesses `device_at` has already let through, so
+    // checking their first byte is enough
+    fn is_mapped(&self, adr: u64) -> bool {
+        self.devices.iter().any(|(range, _)| range.contains(&adr))
+    }
+
+    // Reads `width` bytes zero extended to 64 bits
+    fn load(&mut self, adr: u64, width: u64) -> Result<u64, VmError> {
+        let value = match self.device_at(adr, width)? {
+            Some((offset, device)) => device.borrow_mut().read(offset, width),
+            None => self.memory.read_uint(adr, width),
+        }
+        .map_err(|reason| self.trap(reason))?;
+
+        self.record_access(adr, width, Access::Read, value, value);
+        Ok(value)
+    }
+
+    // Writes the low `width` bytes of `value`
+    fn store_memory(&mut self, adr: u64, width: u64, value: u64) -> Result<(), VmError> {
+        let value = zero_extend(value, width);
+        let old_value = match self.device_at(adr, width)? {
+            // Reading a device can have side effects, so its old value is
+            // reported as 0
+            Some((offset, device)) => device.borrow_mut().write(offset, width, value).map(|_| 0),
+            None => self
+                .memory
+                .read_uint(adr, width)
+                .and_then(|old_value| self.memory.write_uint(adr, width, value).map(|_| old_value)),
+        }
+        .map_err(|reason| self.trap(reason))?;
+
+        self.record_access(adr, width, Access::Write, old_value, value);
+        Ok(())
+    }
+
+    fn resolve_operand(&mut self, operand: &Operand) -> Result<u64, VmError> {
+        self.resolve_operand_sized(operand, 8)
+    }
+
+    // Memory operands only read `width` bytes, registers and immediates are
+    // returned whole
+    fn resolve_operand_sized(&mut self, operand: &Operand, width: u64) -> Result<u64, VmError> {
         match operand {
-            Operand::Data(reg) => self.registers.get(reg),
-            Operand::Adr(reg) => {
-                let offset = self.registers.get(reg);
-                // Find offset in memory and read 8 bytes forward
-                let bytes: [u8; 8] = [
-                    self.memory[usize::try_from(offset)
-                        .expect("This VM can only function on 64 bit machines.")],
-                    self.memory[usize::try_from(offset + 1)
-                        .expect("This VM can only function on 64 bit machines.")],
-                    self.memory[usize::try_from(offset + 2)
-                        .expect("This VM can only function on 64 bit machines.")],
-                    self.memory[usize::try_from(offset + 3)
-                        .expect("This VM can only function on 64 bit machines.")],
-                    self.memory[usize::try_from(offset + 4)
-                        .expect("This VM can only function on 64 bit machines.")],
-                    self.memory[usize::try_from(offset + 5)
-                        .expect("This VM can only function on 64 bit machines.")],
-                    self.memory[usize::try_from(offset + 6)
-                        .expect("This VM can only function on 64 bit machines.")],
-                    self.memory[usize::try_from(offset + 7)
-                        .expect("This VM can only function on 64 bit machines.")],
-                ];
-                u64::from_ne_bytes(bytes)
-            }
-            Operand::Imm(imm) => imm.as_u64(),
-        }
-    }
-
-    fn get_inst_offset(&self, target: &Target) -> u64 {
+            Operand::Data(reg) => Ok(self.registers.get(reg)),
+            Operand::Adr(reg) => self.load(self.registers.get(reg), width),
+            Operand::Imm(imm) => Ok(imm.as_u64()),
+        }
+    }
+
+    fn get_inst_offset(&self, target: &Target) -> Result<u64, VmError> {
         match target {
             Target::Label(label) => {
                 if let Some(inst_offset) = self.block_table.get(label.0.as_str()) {
-                    (*inst_offset) as u64
+                    Ok((*inst_offset) as u64)
                 } else {
-                    panic!("undefined label ID in `jump`")
+                    Err(self.trap(TrapReason::UndefinedLabel(label.0.clone())))
                 }
             }
-            Target::Pointer(reg) => self.registers.get(reg),
+            Target::Pointer(reg) => Ok(self.registers.get(reg)),
         }
     }
 
-    fn store(&mut self, dst: &Operand, src: &Operand) {
+    fn store(&mut self, dst: &Operand, src: &Operand) -> Result<(), VmError> {
+        self.store_sized(dst, src, 8, false)
+    }
+
+    // Moves the low `width` bytes of `src`, registers get them zero or sign
+    // extended depending on `signed`
+    fn store_sized(
+        &mut self,
+        dst: &Operand,
+        src: &Operand,
+        width: u64,
+        signed: bool,
+    ) -> Result<(), VmError> {
         match dst {
-            Operand::Imm(_) => {
-                panic!("move destination cannot be immediate value")
-            }
+            Operand::Imm(_) => return Err(self.trap(TrapReason::ImmediateDestination)),
             Operand::Data(reg) => {
-                self.registers.set(reg, self.resolve_operand(src));
+                let value = self.resolve_operand_sized(src, width)?;
+                let value = if signed {
+                    sign_extend(value, width)
+                } else {
+                    zero_extend(value, width)
+                };
+                self.registers.set(reg, value);
             }
             Operand::Adr(reg) => {
-                let offset = self.registers.get(reg);
-
-                match src {
-                    Operand::Imm(_) | Operand::Data(_) => {
-                        let value = self.resolve_operand(src);
-                        let bytes = value.to_ne_bytes();
-                        self.memory[offset as usize] = bytes[0];
-                        self.memory[(offset + 1) as usize] = bytes[1];
-                        self.memory[(offset + 2) as usize] = bytes[2];
-                        self.memory[(offset + 3) as usize] = bytes[3];
-                        self.memory[(offset + 4) as usize] = bytes[4];
-                        self.memory[(offset + 5) as usize] = bytes[5];
-                        self.memory[(offset + 6) as usize] = bytes[6];
-                        self.memory[(offset + 7) as usize] = bytes[7];
-                    }
-                    Operand::Adr(src_adr_reg) => {
-                        let src_offset = self.registers.get(src_adr_reg);
-                        for i in 0..8 {
-                            self.memory[(offset + i) as usize] =
-                                self.memory[(src_offset + i) as usize];
-                        }
-                    }
-                }
+                // Memory to memory moves go through `load` as well, so watchpoints
+                // see both the read of the source and the write of the destination
+                let value = self.resolve_operand_sized(src, width)?;
+                self.store_memory(self.registers.get(reg), width, value)?;
             }
         }
+
+        Ok(())
     }
 
-    fn execute_syscall(&mut self, id: u64) {
-        match id {
-            0 => {
-                let uint_value = self.registers.get(&Register::R1);
-                writeln!(self.writer, "{}", uint_value).unwrap();
-            }
-            1 => {
-                let int_value = self.registers.get(&Register::R1) as i64;
-                writeln!(self.writer, "{}", int_value).unwrap();
-            }
-            2 => {
-                let float_value = f32::from_bits(self.registers.get(&Register::R1) as u32);
-                writeln!(self.writer, "{}", float_value).unwrap();
-            }
-            3 => {
-                let double_value = f64::from_bits(self.registers.get(&Register::R1));
-                writeln!(self.writer, "{}", double_value).unwrap();
-            }
-            4 => {
-                let duration = self.registers.get(&Register::R1);
-                thread::sleep(Duration::from_millis(duration));
-            }
-            _ => panic!("Unknown syscall ID: {}", id),
+    // Splits `bytes` into the widest stores that fit
+    fn store_bytes(&mut self, adr: u64, bytes: &[u8]) -> Result<(), VmError> {
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let width = [8, 4, 2, 1]
+                .iter()
+                .copied()
+                .find(|width| offset + width <= bytes.len())
+                .unwrap();
+            let mut value = [0; 8];
+            value[..width].copy_from_slice(&bytes[offset..offset + width]);
+
+            let chunk_adr = adr
+                .checked_add(offset as u64)
+                .ok_or_else(|| self.trap(TrapReason::MemoryOutOfBounds(adr)))?;
+            self.store_memory(chunk_adr, width as u64, u64::from_ne_bytes(value))?;
+            offset += width;
+        }
+
+        Ok(())
+    }
+
+    fn execute_syscall(&mut self, id: u64) -> Result<(), VmError> {
+        let mut reader = self.reader.borrow_mut();
+        let mut ctx = SyscallContext {
+            registers: &mut self.registers,
+            memory: &self.memory,
+            writer: &mut self.writer,
+            reader: &mut *reader,
+            clock: &mut self.clock,
+            fs: &mut self.fs,
+            exit_code: None,
+            memory_writes: Vec::new(),
+        };
+        let result = self.syscalls.dispatch(id, &mut ctx);
+        let (exit_code, memory_writes) = (ctx.exit_code, ctx.memory_writes);
+        drop(reader);
+
+        if let Some(code) = exit_code {
+            self.exit_code = Some(code);
+            self.registers.set(&Register::Rip, u64::MAX);
+        }
+        result.map_err(|reason| self.trap(reason))?;
+
+        for (adr, bytes) in memory_writes {
+            self.store_bytes(adr, &bytes)?;
         }
+        Ok(())
     }
 
-    pub fn interpret_inst(&mut self, inst: &Inst) {
+    pub fn interpret_inst(&mut self, inst: &Inst) -> Result<(), VmError> {
         match inst {
             Inst::SysCall(operand) => {
-                let raw_value = self.resolve_operand(operand);
-                self.execute_syscall(raw_value);
+                let raw_value = self.resolve_operand(operand)?;
+                self.execute_syscall(raw_value)?;
             }
 
-            Inst::Move(dst, src) => self.store(dst, src),
+            Inst::Move(dst, src) => self.store(dst, src)?,
+            Inst::Move8(dst, src) => self.store_sized(dst, src, 1, false)?,
+            Inst::Move8S(dst, src) => self.store_sized(dst, src, 1, true)?,
+            Inst::Move16(dst, src) => self.store_sized(dst, src, 2, false)?,
+            Inst::Move16S(dst, src) => self.store_sized(dst, src, 2, true)?,
+            Inst::Move32(dst, src) => self.store_sized(dst, src, 4, false)?,
+            Inst::Move32S(dst, src) => self.store_sized(dst, src, 4, true)?,
 
             Inst::Jump(target) => {
-                let target_inst_offset = self.get_inst_offset(target);
+                let target_inst_offset = self.get_inst_offset(target)?;
                 self.registers.set(&Register::Rip, target_inst_offset);
-                return; // return early to avoid the ip increment
+                return Ok(()); // return early to avoid the ip increment
             }
             Inst::CJump(cond, target) => {
-                if self.resolve_operand(cond) == 1 {
-                    let target_inst_offset = self.get_inst_offset(target);
+                let taken = self.resolve_operand(cond)? == 1;
+                self.branch_outcome = Some(taken);
+                if taken {
+                    let target_inst_offset = self.get_inst_offset(target)?;
                     self.registers.set(&Register::Rip, target_inst_offset);
-                    return;
-                } else {
-                    panic!("undefined target label ID in `cjump`")
+                    return Ok(());
                 }
             }
             Inst::Branch(cond, true_target, false_target) => {
-                let inst_offset = if self.resolve_operand(cond) == 1 {
-                    self.get_inst_offset(true_target)
+                let taken = self.resolve_operand(cond)? == 1;
+                self.branch_outcome = Some(taken);
+                let inst_offset = if taken {
+                    self.get_inst_offset(true_target)?
                 } else {
-                    self.get_inst_offset(false_target)
+                    self.get_inst_offset(false_target)?
                 };
 
                 self.registers.set(&Register::Rip, inst_offset);
-                return;
+                return Ok(());
             }
 
             // Arithmetic operations
             Inst::SAdd(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs) as i64;
-                let rhs_value = self.resolve_operand(rhs) as i64;
-                let sum = lhs_value + rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(sum as u64)));
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                let sum = lhs_value.wrapping_add(rhs_value);
+                self.store(dst, &Operand::Imm(Imm::Int(sum as u64)))?;
             }
             Inst::UAdd(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
-                let sum = lhs_value + rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(sum)));
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                let sum = lhs_value.wrapping_add(rhs_value);
+                self.store(dst, &Operand::Imm(Imm::Int(sum)))?;
             }
 
             Inst::Sub(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs) as i64;
-                let rhs_value = self.resolve_operand(rhs) as i64;
-                let difference = lhs_value - rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(difference as u64)));
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                let difference = lhs_value.wrapping_sub(rhs_value);
+                self.store(dst, &Operand::Imm(Imm::Int(difference as u64)))?;
             }
 
             Inst::SMul(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs) as i64;
-                let rhs_value = self.resolve_operand(rhs) as i64;
-                let product = lhs_value * rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(product as u64)));
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                let product = lhs_value.wrapping_mul(rhs_value);
+                self.store(dst, &Operand::Imm(Imm::Int(product as u64)))?;
             }
             Inst::UMul(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
-                let product = lhs_value * rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(product)));
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                let product = lhs_value.wrapping_mul(rhs_value);
+                self.store(dst, &Operand::Imm(Imm::Int(product)))?;
             }
 
             Inst::SDiv(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs) as i64;
-                let rhs_value = self.resolve_operand(rhs) as i64;
-                let quotient = lhs_value / rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(quotient as u64)));
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                if rhs_value == 0 {
+                    return Err(self.trap(TrapReason::DivisionByZero));
+                }
+                let quotient = lhs_value.wrapping_div(rhs_value);
+                self.store(dst, &Operand::Imm(Imm::Int(quotient as u64)))?;
             }
             Inst::UDiv(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                if rhs_value == 0 {
+                    return Err(self.trap(TrapReason::DivisionByZero));
+                }
                 let quotient = lhs_value / rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(quotient)));
+                self.store(dst, &Operand::Imm(Imm::Int(quotient)))?;
             }
 
             Inst::SRem(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs) as i64;
-                let rhs_value = self.resolve_operand(rhs) as i64;
-                let remainder = lhs_value % rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(remainder as u64)));
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                if rhs_value == 0 {
+                    return Err(self.trap(TrapReason::DivisionByZero));
+                }
+                let remainder = lhs_value.wrapping_rem(rhs_value);
+                self.store(dst, &Operand::Imm(Imm::Int(remainder as u64)))?;
             }
             Inst::URem(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                if rhs_value == 0 {
+                    return Err(self.trap(TrapReason::DivisionByZero));
+                }
                 let remainder = lhs_value % rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Int(remainder)));
+                self.store(dst, &Operand::Imm(Imm::Int(remainder)))?;
+            }
+
+            Inst::SAddChecked(dst, lhs, rhs) => {
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                let sum = match lhs_value.checked_add(rhs_value) {
+                    Some(sum) => sum,
+                    None => return Err(self.trap(TrapReason::Overflow)),
+                };
+                self.store(dst, &Operand::Imm(Imm::Int(sum as u64)))?;
+            }
+            Inst::UAddChecked(dst, lhs, rhs) => {
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                let sum = match lhs_value.checked_add(rhs_value) {
+                    Some(sum) => sum,
+                    None => return Err(self.trap(TrapReason::Overflow)),
+                };
+                self.store(dst, &Operand::Imm(Imm::Int(sum)))?;
+            }
+            Inst::SSubChecked(dst, lhs, rhs) => {
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                let difference = match lhs_value.checked_sub(rhs_value) {
+                    Some(difference) => difference,
+                    None => return Err(self.trap(TrapReason::Overflow)),
+                };
+                self.store(dst, &Operand::Imm(Imm::Int(difference as u64)))?;
+            }
+            Inst::USubChecked(dst, lhs, rhs) => {
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                let difference = match lhs_value.checked_sub(rhs_value) {
+                    Some(difference) => difference,
+                    None => return Err(self.trap(TrapReason::Overflow)),
+                };
+                self.store(dst, &Operand::Imm(Imm::Int(difference)))?;
+            }
+            Inst::SMulChecked(dst, lhs, rhs) => {
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                let product = match lhs_value.checked_mul(rhs_value) {
+                    Some(product) => product,
+                    None => return Err(self.trap(TrapReason::Overflow)),
+                };
+                self.store(dst, &Operand::Imm(Imm::Int(product as u64)))?;
+            }
+            Inst::UMulChecked(dst, lhs, rhs) => {
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                let product = match lhs_value.checked_mul(rhs_value) {
+                    Some(product) => product,
+                    None => return Err(self.trap(TrapReason::Overflow)),
+                };
+                self.store(dst, &Operand::Imm(Imm::Int(product)))?;
+            }
+            Inst::SDivChecked(dst, lhs, rhs) => {
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
+                if rhs_value == 0 {
+                    return Err(self.trap(TrapReason::DivisionByZero));
+                }
+                let quotient = match lhs_value.checked_div(rhs_value) {
+                    Some(quotient) => quotient,
+                    None => return Err(self.trap(TrapReason::Overflow)),
+                };
+                self.store(dst, &Operand::Imm(Imm::Int(quotient as u64)))?;
             }
 
             Inst::FAdd(dst, lhs, rhs) => {
-                let lhs_value = f64::from_bits(self.resolve_operand(lhs));
-                let rhs_value = f64::from_bits(self.resolve_operand(rhs));
+                let lhs_value = f64::from_bits(self.resolve_operand(lhs)?);
+                let rhs_value = f64::from_bits(self.resolve_operand(rhs)?);
+                let sum = lhs_value + rhs_value;
+                self.store(dst, &Operand::Imm(Imm::Float(sum)))?;
+            }
+            Inst::FAdd32(dst, lhs, rhs) => {
+                let lhs_value = f32::from_bits(self.resolve_operand(lhs)? as u32);
+                let rhs_value = f32::from_bits(self.resolve_operand(rhs)? as u32);
                 let sum = lhs_value + rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Float(sum)));
+                self.store(dst, &Operand::Imm(Imm::Int(sum.to_bits() as u64)))?;
             }
             Inst::FSub(dst, lhs, rhs) => {
-                let lhs_value = f64::from_bits(self.resolve_operand(lhs));
-                let rhs_value = f64::from_bits(self.resolve_operand(rhs));
+                let lhs_value = f64::from_bits(self.resolve_operand(lhs)?);
+                let rhs_value = f64::from_bits(self.resolve_operand(rhs)?);
+                let difference = lhs_value - rhs_value;
+                self.store(dst, &Operand::Imm(Imm::Float(difference)))?;
+            }
+            Inst::FSub32(dst, lhs, rhs) => {
+                let lhs_value = f32::from_bits(self.resolve_operand(lhs)? as u32);
+                let rhs_value = f32::from_bits(self.resolve_operand(rhs)? as u32);
                 let difference = lhs_value - rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Float(difference)));
+                self.store(dst, &Operand::Imm(Imm::Int(difference.to_bits() as u64)))?;
             }
             Inst::FMul(dst, lhs, rhs) => {
-                let lhs_value = f64::from_bits(self.resolve_operand(lhs));
-                let rhs_value = f64::from_bits(self.resolve_operand(rhs));
+                let lhs_value = f64::from_bits(self.resolve_operand(lhs)?);
+                let rhs_value = f64::from_bits(self.resolve_operand(rhs)?);
+                let product = lhs_value * rhs_value;
+                self.store(dst, &Operand::Imm(Imm::Float(product)))?;
+            }
+            Inst::FMul32(dst, lhs, rhs) => {
+                let lhs_value = f32::from_bits(self.resolve_operand(lhs)? as u32);
+                let rhs_value = f32::from_bits(self.resolve_operand(rhs)? as u32);
                 let product = lhs_value * rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Float(product)));
+                self.store(dst, &Operand::Imm(Imm::Int(product.to_bits() as u64)))?;
             }
             Inst::FDiv(dst, lhs, rhs) => {
-                let lhs_value = f64::from_bits(self.resolve_operand(lhs));
-                let rhs_value = f64::from_bits(self.resolve_operand(rhs));
+                let lhs_value = f64::from_bits(self.resolve_operand(lhs)?);
+                let rhs_value = f64::from_bits(self.resolve_operand(rhs)?);
                 let quotient = lhs_value / rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Float(quotient)));
+                self.store(dst, &Operand::Imm(Imm::Float(quotient)))?;
+            }
+            Inst::FDiv32(dst, lhs, rhs) => {
+                let lhs_value = f32::from_bits(self.resolve_operand(lhs)? as u32);
+                let rhs_value = f32::from_bits(self.resolve_operand(rhs)? as u32);
+                let quotient = lhs_value / rhs_value;
+                self.store(dst, &Operand::Imm(Imm::Int(quotient.to_bits() as u64)))?;
             }
             Inst::FRem(dst, lhs, rhs) => {
-                let lhs_value = f64::from_bits(self.resolve_operand(lhs));
-                let rhs_value = f64::from_bits(self.resolve_operand(rhs));
+                let lhs_value = f64::from_bits(self.resolve_operand(lhs)?);
+                let rhs_value = f64::from_bits(self.resolve_operand(rhs)?);
+                let remainder = lhs_value % rhs_value;
+                self.store(dst, &Operand::Imm(Imm::Float(remainder)))?;
+            }
+            Inst::FRem32(dst, lhs, rhs) => {
+                let lhs_value = f32::from_bits(self.resolve_operand(lhs)? as u32);
+                let rhs_value = f32::from_bits(self.resolve_operand(rhs)? as u32);
                 let remainder = lhs_value % rhs_value;
-                self.store(dst, &Operand::Imm(Imm::Float(remainder)));
+                self.store(dst, &Operand::Imm(Imm::Int(remainder.to_bits() as u64)))?;
+            }
+
+            // Conversions
+            Inst::SToF(dst, src) => {
+                let value = self.resolve_operand(src)? as i64;
+                self.store(dst, &Operand::Imm(Imm::Float(value as f64)))?;
+            }
+            Inst::UToF(dst, src) => {
+                let value = self.resolve_operand(src)?;
+                self.store(dst, &Operand::Imm(Imm::Float(value as f64)))?;
+            }
+            Inst::FToS(dst, src) => {
+                let value = f64::from_bits(self.resolve_operand(src)?);
+                self.store(dst, &Operand::Imm(Imm::Int(value as i64 as u64)))?;
+            }
+            Inst::FToU(dst, src) => {
+                let value = f64::from_bits(self.resolve_operand(src)?);
+                self.store(dst, &Operand::Imm(Imm::Int(value as u64)))?;
+            }
+            Inst::FToSRound(dst, src) => {
+                let value = f64::from_bits(self.resolve_operand(src)?);
+                self.store(dst, &Operand::Imm(Imm::Int(value.round() as i64 as u64)))?;
+            }
+            Inst::FToURound(dst, src) => {
+                let value = f64::from_bits(self.resolve_operand(src)?);
+                self.store(dst, &Operand::Imm(Imm::Int(value.round() as u64)))?;
+            }
+            Inst::F32ToF64(dst, src) => {
+                let value = f32::from_bits(self.resolve_operand(src)? as u32);
+                self.store(dst, &Operand::Imm(Imm::Float(value as f64)))?;
+            }
+            Inst::F64ToF32(dst, src) => {
+                let value = f64::from_bits(self.resolve_operand(src)?);
+                self.store(
+                    dst,
+                    &Operand::Imm(Imm::Int((value as f32).to_bits() as u64)),
+                )?;
             }
 
             // Bitwise operations
             Inst::Shl(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
-                self.store(dst, &Operand::Imm(Imm::Int(lhs_value << rhs_value)));
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                self.store(
+                    dst,
+                    &Operand::Imm(Imm::Int(lhs_value.wrapping_shl(rhs_value as u32))),
+                )?;
             }
             Inst::Shr(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
-                self.store(dst, &Operand::Imm(Imm::Int(lhs_value >> rhs_value)));
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                self.store(
+                    dst,
+                    &Operand::Imm(Imm::Int(lhs_value.wrapping_shr(rhs_value as u32))),
+                )?;
             }
             Inst::And(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
-                self.store(dst, &Operand::Imm(Imm::Int(lhs_value & rhs_value)));
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                self.store(dst, &Operand::Imm(Imm::Int(lhs_value & rhs_value)))?;
             }
             Inst::Or(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
-                self.store(dst, &Operand::Imm(Imm::Int(lhs_value | rhs_value)));
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                self.store(dst, &Operand::Imm(Imm::Int(lhs_value | rhs_value)))?;
             }
             Inst::Xor(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
-                self.store(dst, &Operand::Imm(Imm::Int(lhs_value ^ rhs_value)));
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
+                self.store(dst, &Operand::Imm(Imm::Int(lhs_value ^ rhs_value)))?;
             }
             Inst::Not(dst, src) => {
-                let value = self.resolve_operand(src);
+                let value = self.resolve_operand(src)?;
                 let result = !value;
-                self.store(dst, &Operand::Imm(Imm::Int(result)));
+                self.store(dst, &Operand::Imm(Imm::Int(result)))?;
             }
 
             // Comparative operations
             Inst::Eq(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
                 self.store(
                     dst,
                     &Operand::Imm(if lhs_value == rhs_value {
@@ -312,11 +871,11 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
                     } else {
                         Imm::False
                     }),
-                );
+                )?;
             }
             Inst::FEq(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
 
                 // We directly compare the bits instad of converting back to f64
                 self.store(
@@ -326,12 +885,26 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
                     } else {
                         Imm::False
                     }),
-                );
+                )?;
+            }
+            Inst::FEq32(dst, lhs, rhs) => {
+                let lhs_value = self.resolve_operand(lhs)? as u32;
+                let rhs_value = self.resolve_operand(rhs)? as u32;
+
+                // We directly compare the bits instead of converting back to f32
+                self.store(
+                    dst,
+                    &Operand::Imm(if lhs_value == rhs_value {
+                        Imm::True
+                    } else {
+                        Imm::False
+                    }),
+                )?;
             }
 
             Inst::SLt(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs) as i64;
-                let rhs_value = self.resolve_operand(rhs) as i64;
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
                 self.store(
                     dst,
                     &Operand::Imm(if lhs_value < rhs_value {
@@ -339,11 +912,11 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
                     } else {
                         Imm::False
                     }),
-                );
+                )?;
             }
             Inst::ULt(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
                 self.store(
                     dst,
                     &Operand::Imm(if lhs_value < rhs_value {
@@ -351,11 +924,11 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
                     } else {
                         Imm::False
                     }),
-                );
+                )?;
             }
             Inst::FLt(dst, lhs, rhs) => {
-                let lhs_value = f64::from_bits(self.resolve_operand(lhs));
-                let rhs_value = f64::from_bits(self.resolve_operand(rhs));
+                let lhs_value = f64::from_bits(self.resolve_operand(lhs)?);
+                let rhs_value = f64::from_bits(self.resolve_operand(rhs)?);
                 self.store(
                     dst,
                     &Operand::Imm(if lhs_value < rhs_value {
@@ -363,12 +936,24 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
                     } else {
                         Imm::False
                     }),
-                );
+                )?;
+            }
+            Inst::FLt32(dst, lhs, rhs) => {
+                let lhs_value = f32::from_bits(self.resolve_operand(lhs)? as u32);
+                let rhs_value = f32::from_bits(self.resolve_operand(rhs)? as u32);
+                self.store(
+                    dst,
+                    &Operand::Imm(if lhs_value < rhs_value {
+                        Imm::True
+                    } else {
+                        Imm::False
+                    }),
+                )?;
             }
 
             Inst::SGt(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs) as i64;
-                let rhs_value = self.resolve_operand(rhs) as i64;
+                let lhs_value = self.resolve_operand(lhs)? as i64;
+                let rhs_value = self.resolve_operand(rhs)? as i64;
                 self.store(
                     dst,
                     &Operand::Imm(if lhs_value > rhs_value {
@@ -376,11 +961,11 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
                     } else {
                         Imm::False
                     }),
-                );
+                )?;
             }
             Inst::UGt(dst, lhs, rhs) => {
-                let lhs_value = self.resolve_operand(lhs);
-                let rhs_value = self.resolve_operand(rhs);
+                let lhs_value = self.resolve_operand(lhs)?;
+                let rhs_value = self.resolve_operand(rhs)?;
                 self.store(
                     dst,
                     &Operand::Imm(if lhs_value > rhs_value {
@@ -388,11 +973,11 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
                     } else {
                         Imm::False
                     }),
-                );
+                )?;
             }
             Inst::FGt(dst, lhs, rhs) => {
-                let lhs_value = f64::from_bits(self.resolve_operand(lhs));
-                let rhs_value = f64::from_bits(self.resolve_operand(rhs));
+                let lhs_value = f64::from_bits(self.resolve_operand(lhs)?);
+                let rhs_value = f64::from_bits(self.resolve_operand(rhs)?);
                 self.store(
                     dst,
                     &Operand::Imm(if lhs_value > rhs_value {
@@ -400,7 +985,19 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
                     } else {
                         Imm::False
                     }),
-                );
+                )?;
+            }
+            Inst::FGt32(dst, lhs, rhs) => {
+                let lhs_value = f32::from_bits(self.resolve_operand(lhs)? as u32);
+                let rhs_value = f32::from_bits(self.resolve_operand(rhs)? as u32);
+                self.store(
+                    dst,
+                    &Operand::Imm(if lhs_value > rhs_value {
+                        Imm::True
+                    } else {
+                        Imm::False
+                    }),
+                )?;
             }
         }
 
@@ -408,26 +1005,138 @@ impl<'a, W: Write, const MEMORY_SIZE: usize> VM<'a, W, MEMORY_SIZE> {
         if rip != u64::MAX {
             self.registers.set(&Register::Rip, rip + 1);
         }
+
+        Ok(())
     }
 
-    pub fn interpret(&mut self) {
-        if let Some(main_routine_begin) = self.block_table.get("main") {
-            let main_routine_begin = *main_routine_begin;
-            self.registers
-                .set(&Register::Rip, main_routine_begin as u64);
+    fn exit_status(&self, trap: Option<VmError>) -> ExitStatus {
+        let (code, reason) = match (trap, self.exit_code) {
+            (Some(err), _) => (1, HaltReason::Trapped(err)),
+            (None, Some(code)) => (code, HaltReason::Exited),
+            (None, None) => (0, HaltReason::Returned),
+        };
 
-            while self.registers.get(&Register::Rip) != u64::MAX {
-                let inst = if let Some(inst) = self
-                    .insts
-                    .get((self.registers.get(&Register::Rip)) as usize)
-                {
-                    inst.clone()
-                } else {
-                    unreachable!()
-                };
+        ExitStatus {
+            code,
+            reason,
+            perf: self.perf,
+            cache: self.cache.as_ref().map(Cache::stats),
+            branches: self.branch_stats().cloned(),
+        }
+    }
+
+    // Memory accesses are counted as they happen, see `record_access`
+    fn count_inst(&mut self, inst: &Inst, rip: u64) {
+        let class = inst.class();
+        self.perf.insts_retired += 1;
+        self.perf.cycles += self.cost_model.inst_cost(class);
+
+        let branch_outcome = self.branch_outcome.take();
+        if let (Some(taken), Some(predictor)) = (branch_outcome, &self.predictor) {
+            let mut predictor = predictor.borrow_mut();
+            let predicted = predictor.predict(rip);
+            predictor.update(rip, taken);
+            self.branch_stats.record(rip, taken, predicted);
+            if predicted != taken {
+                self.perf.cycles += self.cost_model.branch_mispredict_cost();
+            }
+        }
+
+        match (class, branch_outcome) {
+            (InstClass::Jump, _) | (InstClass::Branch, Some(true)) => self.perf.branches_taken += 1,
+            (InstClass::Branch, _) => self.perf.branches_not_taken += 1,
+            (InstClass::SysCall, _) => self.perf.syscalls += 1,
+            _ => {}
+        }
+    }
+
+    // Executes the instruction under `Rip`, does nothing once the VM has halted.
+    // Returns the watchpoint hit by the instruction, if any
+    pub fn step(&mut self) -> Result<Option<WatchpointHit>, VmError> {
+        if self.is_halted() {
+            return Ok(None);
+        }
+
+        let inst = if let Some(inst) = self.current_inst() {
+            inst.clone()
+        } else {
+            return Err(self.trap(TrapReason::RipOutOfBounds(self.rip())));
+        };
+
+        let rip = self.rip();
+        let registers_before = self.registers;
+        let perf_before = self.perf;
+
+        self.resuming = false;
+        self.watchpoint_hit = None;
+        self.branch_outcome = None;
+        self.memory_accesses.clear();
+        self.interpret_inst(&inst)?;
+        self.count_inst(&inst, rip);
+        self.clock
+            .advance_cycles(self.perf.cycles - perf_before.cycles);
+        for (_, device) in &self.devices {
+            device.borrow_mut().tick();
+        }
 
-                self.interpret_inst(&inst);
+        if let Some(undo_log) = &mut self.undo_log {
+            undo_log.push(UndoEntry {
+                registers: registers_before,
+                perf: perf_before,
+                memory_writes: self
+                    .memory_accesses
+                    .iter()
+                    .filter(|memory_access| memory_access.access == Access::Write)
+                    .map(|memory_access| {
+                        (
+                            memory_access.adr,
+                            memory_access.size,
+                            memory_access.old_value,
+                        )
+                    })
+                    .collect(),
+            });
+        }
+
+        if let Some(tracer) = &mut self.tracer {
+            let traced = tracer.record(
+                rip,
+                &inst.as_asm(),
+                &registers_before,
+                &self.registers,
+                &self.memory_accesses,
+            );
+            traced.map_err(|_| self.trap(TrapReason::OutputFailed))?;
+        }
+
+        Ok(self.watchpoint_hit.take())
+    }
+
+    pub fn run_until_breakpoint(&mut self) -> Result<Stop, VmError> {
+        while !self.is_halted() {
+            let rip = self.rip();
+            if !self.resuming && self.breakpoints.contains(&rip) {
+                self.resuming = true;
+                return Ok(Stop::Breakpoint(rip));
+            }
+
+            if let Some(hit) = self.step()? {
+                return Ok(Stop::Watchpoint(hit));
             }
         }
+
+        Ok(Stop::Halted(self.exit_status(None)))
+    }
+
+    // Runs until the program returns, exits or traps, ignoring breakpoints and
+    // watchpoints
+    pub fn interpret(&mut self) -> ExitStatus {
+        while !self.is_halted() {
+            if let Err(err) = self.step() {
+                return self.exit_status(Some(err));
+            }
+        }
+
+        self.exit_status(None)
     }
 }
@@ -0,0 +1,80 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockMode {
+    // Time is whatever the host says and `sleep` blocks the calling thread
+    Wall,
+    // Time only moves as instructions retire and programs sleep, so runs are
+    // reproducible and `sleep` returns immediately
+    Virtual,
+}
+
+// The time source behind the `sleep` and `time_now` syscalls. Times are in
+// nanoseconds since the clock was created.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    mode: ClockMode,
+    // Only set in wall clock mode, `Instant` isn't available on every target
+    start: Option<Instant>,
+    elapsed_ns: u64,
+    cycle_ns: u64,
+}
+
+impl Default for Clock {
+    // A virtual clock running at 1 GHz
+    fn default() -> Self {
+        Self::virtual_time(1)
+    }
+}
+
+impl Clock {
+    pub fn wall_time() -> Self {
+        Self {
+            mode: ClockMode::Wall,
+            start: Some(Instant::now()),
+            elapsed_ns: 0,
+            cycle_ns: 0,
+        }
+    }
+
+    // Every cycle charged by the cost model advances the clock by `cycle_ns`
+    pub fn virtual_time(cycle_ns: u64) -> Self {
+        Self {
+            mode: ClockMode::Virtual,
+            start: None,
+            elapsed_ns: 0,
+            cycle_ns,
+        }
+    }
+
+    pub fn mode(&self) -> ClockMode {
+        self.mode
+    }
+
+    pub fn now_ns(&self) -> u64 {
+        match self.start {
+            Some(start) => start.elapsed().as_nanos() as u64,
+            None => self.elapsed_ns,
+        }
+    }
+
+    pub fn sleep(&mut self, duration: Duration) {
+        match self.mode {
+            ClockMode::Wall => thread::sleep(duration),
+            ClockMode::Virtual => {
+                self.elapsed_ns = self.elapsed_ns.saturating_add(duration.as_nanos() as u64)
+            }
+        }
+    }
+
+    pub(crate) fn advance_cycles(&mut self, cycles: u64) {
+        if self.mode == ClockMode::Virtual {
+            self.elapsed_ns = self
+                .elapsed_ns
+                .saturating_add(cycles.saturating_mul(self.cycle_ns));
+        }
+    }
+}
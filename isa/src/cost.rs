@@ -0,0 +1,94 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstClass {
+    SysCall,
+    // Register to register moves and immediate loads
+    Move,
+    Load,
+    Store,
+    Jump,
+    Branch,
+
+    IntAlu,
+    IntMul,
+    IntDiv,
+    FloatAdd,
+    FloatMul,
+    FloatDiv,
+    // Int to float, float to int and float width conversions
+    Convert,
+    Compare,
+}
+
+pub trait CostModel: fmt::Debug {
+    fn inst_cost(&self, class: InstClass) -> u64;
+
+    // Charged on top of `inst_cost` for every memory access whatever its width,
+    // which is what makes a CISC `[%N]` operand more expensive than a register
+    // operand
+    fn memory_access_cost(&self) -> u64;
+
+    // Charged on top of `memory_access_cost` when the VM has a cache attached
+    // and the access misses it
+    fn cache_miss_cost(&self) -> u64 {
+        0
+    }
+
+    // Charged on top of `inst_cost` when the VM has a branch predictor attached
+    // and it guessed the direction of a `CJump` or `Branch` wrong
+    fn branch_mispredict_cost(&self) -> u64 {
+        0
+    }
+}
+
+// Rough latencies of a simple in-order core with a single cycle cache
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultCostModel;
+
+impl CostModel for DefaultCostModel {
+    fn inst_cost(&self, class: InstClass) -> u64 {
+        match class {
+            InstClass::SysCall => 50,
+            InstClass::Move => 1,
+            InstClass::Load => 1,
+            InstClass::Store => 1,
+            InstClass::Jump => 1,
+            InstClass::Branch => 2,
+
+            InstClass::IntAlu => 1,
+            InstClass::IntMul => 3,
+            InstClass::IntDiv => 20,
+            InstClass::FloatAdd => 4,
+            InstClass::FloatMul => 5,
+            InstClass::FloatDiv => 20,
+            InstClass::Convert => 2,
+            InstClass::Compare => 1,
+        }
+    }
+
+    fn memory_access_cost(&self) -> u64 {
+        2
+    }
+
+    fn cache_miss_cost(&self) -> u64 {
+        20
+    }
+
+    fn branch_mispredict_cost(&self) -> u64 {
+        3
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerfCounters {
+    pub insts_retired: u64,
+    pub cycles: u64,
+    pub loads: u64,
+    pub stores: u64,
+    // Jumps are always taken, branches are taken when their condition holds,
+    // even if the target happens to be the next instruction
+    pub branches_taken: u64,
+    pub branches_not_taken: u64,
+    pub syscalls: u64,
+}
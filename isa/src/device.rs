@@ -0,0 +1,142 @@
+use std::{cell::RefCell, fmt, io::Write, ops::Range, rc::Rc};
+
+use crate::shared::TrapReason;
+
+// A memory mapped device. Offsets are relative to the start of the range the
+// device is mapped at and widths are in bytes, values use the same byte order
+// as memory.
+pub trait Device: fmt::Debug {
+    fn read(&mut self, offset: u64, width: u64) -> Result<u64, TrapReason>;
+    fn write(&mut self, offset: u64, width: u64, value: u64) -> Result<(), TrapReason>;
+
+    // Called once after every executed instruction
+    fn tick(&mut self) {}
+}
+
+// Devices are shared with the caller so their state can be inspected while and
+// after the VM runs
+pub type SharedDevice<'a> = Rc<RefCell<dyn Device + 'a>>;
+
+// Writing to offset 0 outputs the low byte of the value, reads return 0
+pub struct Console<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> fmt::Debug for Console<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Console").finish()
+    }
+}
+
+impl<W: Write> Console<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+}
+
+impl<W: Write> Device for Console<W> {
+    fn read(&mut self, _offset: u64, _width: u64) -> Result<u64, TrapReason> {
+        Ok(0)
+    }
+
+    fn write(&mut self, offset: u64, _width: u64, value: u64) -> Result<(), TrapReason> {
+        if offset == 0 {
+            self.writer
+                .write_all(&[value as u8])
+                .map_err(|_| TrapReason::OutputFailed)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Offset 0 reads the number of instructions executed since the timer was
+// mapped or last reset, any write to it resets the count
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timer {
+    ticks: u64,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Device for Timer {
+    fn read(&mut self, offset: u64, _width: u64) -> Result<u64, TrapReason> {
+        Ok(if offset == 0 { self.ticks } else { 0 })
+    }
+
+    fn write(&mut self, offset: u64, _width: u64, _value: u64) -> Result<(), TrapReason> {
+        if offset == 0 {
+            self.ticks = 0;
+        }
+
+        Ok(())
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+    }
+}
+
+// One byte per pixel, row by row, so pixel (x, y) lives at offset
+// `y * width + x`
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    width: u64,
+    height: u64,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u64, height: u64) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn pixel_range(&self, offset: u64, width: u64) -> Result<Range<usize>, TrapReason> {
+        match offset.checked_add(width) {
+            Some(end) if width <= 8 && end <= self.pixels.len() as u64 => {
+                Ok(offset as usize..end as usize)
+            }
+            _ => Err(TrapReason::MemoryOutOfBounds(offset)),
+        }
+    }
+}
+
+impl Device for Framebuffer {
+    fn read(&mut self, offset: u64, width: u64) -> Result<u64, TrapReason> {
+        let range = self.pixel_range(offset, width)?;
+        let mut bytes = [0; 8];
+        bytes[..range.len()].copy_from_slice(&self.pixels[range]);
+        Ok(u64::from_ne_bytes(bytes))
+    }
+
+    fn write(&mut self, offset: u64, width: u64, value: u64) -> Result<(), TrapReason> {
+        let range = self.pixel_range(offset, width)?;
+        let len = range.len();
+        self.pixels[range].copy_from_slice(&value.to_ne_bytes()[..len]);
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    UndefinedLabel(String),
+    // The label's instruction index needs more bits than the field holding it
+    TargetOutOfRange(String, u32),
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndefinedLabel(label) => write!(f, "undefined label `{}`", label),
+            Self::TargetOutOfRange(label, bits) => {
+                write!(f, "jump target `{}` does not fit in {} bits", label, bits)
+            }
+        }
+    }
+}
+
+impl Error for EncodeError {}
+
+// Offsets are in bytes from the start of the machine code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEnd(usize),
+    UnknownOpcode(usize, u8),
+    InvalidRegister(usize, u8),
+    InvalidMode(usize, u8),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd(offset) => {
+                write!(f, "{}: unexpected end of machine code", offset)
+            }
+            Self::UnknownOpcode(offset, opcode) => {
+                write!(f, "{}: unknown opcode {:#04x}", offset, opcode)
+            }
+            Self::InvalidRegister(offset, id) => {
+                write!(f, "{}: invalid register ID {}", offset, id)
+            }
+            Self::InvalidMode(offset, mode) => {
+                write!(f, "{}: invalid operand mode {:#05b}", offset, mode)
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+// Whether the value survives a round trip through a sign extended field of `bits` bits
+pub(crate) fn fits_signed(value: u64, bits: u32) -> bool {
+    let shift = 64 - bits;
+    (((value << shift) as i64) >> shift) as u64 == value
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    io::SeekFrom,
+};
+
+// Descriptors 0 to 2 are left free so they can't be mistaken for the usual
+// standard streams
+const FIRST_FD: u64 = 3;
+
+// Files can't grow past this and positions can't be moved past it, otherwise a
+// single `seek` and `write` from the program could make the host allocate as
+// much as it asked for
+pub const MAX_FILE_SIZE: u64 = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    // The file has to exist
+    Read,
+    // Creates the file or truncates it
+    Write,
+    // Creates the file, every write goes to its end
+    Append,
+}
+
+impl OpenMode {
+    pub fn from_id(id: u64) -> Option<Self> {
+        match id {
+            0 => Some(Self::Read),
+            1 => Some(Self::Write),
+            2 => Some(Self::Append),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OpenFile {
+    path: String,
+    mode: OpenMode,
+    pos: u64,
+}
+
+// A flat, in-memory filesystem the file syscalls operate on. It never touches
+// the host filesystem, the embedder fills it before running a program and
+// reads the results back afterwards. Failed operations return `None`, which
+// the syscalls report to the program as `u64::MAX`.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualFs {
+    files: BTreeMap<String, Vec<u8>>,
+    open_files: HashMap<u64, OpenFile>,
+    next_fd: u64,
+}
+
+impl VirtualFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Creates or replaces a file
+    pub fn insert(&mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(path.into(), contents.into());
+    }
+
+    pub fn remove(&mut self, path: &str) -> Option<Vec<u8>> {
+        self.files.remove(path)
+    }
+
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(Vec::as_slice)
+    }
+
+    // Every file in path order
+    pub fn files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.files
+            .iter()
+            .map(|(path, contents)| (path.as_str(), contents.as_slice()))
+    }
+
+    pub fn open(&mut self, path: &str, mode: OpenMode) -> Option<u64> {
+        let pos = match mode {
+            OpenMode::Read => {
+                self.files.get(path)?;
+                0
+            }
+            OpenMode::Write => {
+                self.files.insert(path.to_string(), Vec::new());
+                0
+            }
+            OpenMode::Append => self.files.entry(path.to_string()).or_default().len() as u64,
+        };
+
+        let fd = FIRST_FD + self.next_fd;
+        self.next_fd += 1;
+        self.open_files.insert(
+            fd,
+            OpenFile {
+                path: path.to_string(),
+                mode,
+                pos,
+            },
+        );
+        Some(fd)
+    }
+
+    // Returns the number of bytes read, 0 at the end of the file
+    pub fn read(&mut self, fd: u64, buf: &mut [u8]) -> Option<usize> {
+        let file = self.open_files.get_mut(&fd)?;
+        if file.mode != OpenMode::Read {
+            return None;
+        }
+
+        let contents = self.files.get(&file.path)?;
+        let start = usize::try_from(file.pos).ok()?.min(contents.len());
+        let len = buf.len().min(contents.len() - start);
+        buf[..len].copy_from_slice(&contents[start..start + len]);
+        file.pos += len as u64;
+        Some(len)
+    }
+
+    // Writing past the end of the file fills the gap with zeros, writes that
+    // would end past `MAX_FILE_SIZE` fail without writing anything
+    pub fn write(&mut self, fd: u64, bytes: &[u8]) -> Option<usize> {
+        let file = self.open_files.get_mut(&fd)?;
+        let contents = match file.mode {
+            OpenMode::Read => return None,
+            OpenMode::Write => self.files.entry(file.path.clone()).or_default(),
+            OpenMode::Append => {
+                let contents = self.files.entry(file.path.clone()).or_default();
+                file.pos = contents.len() as u64;
+                contents
+            }
+        };
+
+        let end = file.pos.checked_add(bytes.len() as u64)?;
+        if end > MAX_FILE_SIZE {
+            return None;
+        }
+
+        let (start, end) = (file.pos as usize, end as usize);
+        if contents.len() < end {
+            contents.resize(end, 0);
+        }
+        contents[start..end].copy_from_slice(bytes);
+        file.pos = end as u64;
+        Some(bytes.len())
+    }
+
+    // Returns the new position from the start of the file, which can be past
+    // its end but not past `MAX_FILE_SIZE`
+    pub fn seek(&mut self, fd: u64, pos: SeekFrom) -> Option<u64> {
+        let file = self.open_files.get_mut(&fd)?;
+        let len = self.files.get(&file.path).map_or(0, Vec::len) as u64;
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => add_offset(file.pos, offset),
+            SeekFrom::End(offset) => add_offset(len, offset),
+        }
+        .filter(|new_pos| *new_pos <= MAX_FILE_SIZE)?;
+
+        file.pos = new_pos;
+        Some(new_pos)
+    }
+
+    pub fn close(&mut self, fd: u64) -> Option<()> {
+        self.open_files.remove(&fd).map(|_| ())
+    }
+}
+
+fn add_offset(pos: u64, offset: i64) -> Option<u64> {
+    if offset < 0 {
+        pos.checked_sub(offset.unsigned_abs())
+    } else {
+        pos.checked_add(offset as u64)
+    }
+}
@@ -1,3 +1,14 @@
+pub mod asm;
+pub mod cache;
 pub mod cisc;
+pub mod clock;
+pub mod cost;
+pub mod device;
+pub mod encoding;
+pub mod fs;
+pub mod memory;
+pub mod predictor;
 pub mod risc;
 pub mod shared;
+pub mod syscall;
+pub mod trace;
@@ -0,0 +1,130 @@
+use std::{collections::HashMap, fmt, ops::Range};
+
+use crate::shared::TrapReason;
+
+pub const PAGE_SIZE: u64 = 4096;
+
+#[derive(Clone)]
+enum Backing {
+    Flat(Vec<u8>),
+    // Pages are only allocated the first time something non-zero is written to
+    // them, every other page reads as zeros
+    Sparse(HashMap<u64, Box<[u8]>>),
+}
+
+// The VM's address space, every access is bounds checked against `size`
+#[derive(Clone)]
+pub struct Memory {
+    size: u64,
+    backing: Backing,
+}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.backing {
+            Backing::Flat(_) => f.debug_struct("Memory").field("size", &self.size).finish(),
+            Backing::Sparse(pages) => f
+                .debug_struct("Memory")
+                .field("size", &self.size)
+                .field("resident_pages", &pages.len())
+                .finish(),
+        }
+    }
+}
+
+impl Memory {
+    // Allocates all of memory upfront
+    pub fn flat(size: u64) -> Self {
+        Self {
+            size,
+            backing: Backing::Flat(vec![0; size as usize]),
+        }
+    }
+
+    // Allocates pages of `PAGE_SIZE` bytes on demand, so a large address space
+    // only costs as much as the memory actually written
+    pub fn sparse(size: u64) -> Self {
+        Self {
+            size,
+            backing: Backing::Sparse(HashMap::new()),
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn check_range(&self, adr: u64, len: usize) -> Result<Range<u64>, TrapReason> {
+        match adr.checked_add(len as u64) {
+            Some(end) if end <= self.size => Ok(adr..end),
+            _ => Err(TrapReason::MemoryOutOfBounds(adr)),
+        }
+    }
+
+    pub fn read(&self, adr: u64, buf: &mut [u8]) -> Result<(), TrapReason> {
+        let range = self.check_range(adr, buf.len())?;
+
+        match &self.backing {
+            Backing::Flat(bytes) => {
+                buf.copy_from_slice(&bytes[range.start as usize..range.end as usize])
+            }
+            Backing::Sparse(pages) => {
+                for (offset, byte) in buf.iter_mut().enumerate() {
+                    let adr = range.start + offset as u64;
+                    *byte = pages
+                        .get(&(adr / PAGE_SIZE))
+                        .map_or(0, |page| page[(adr % PAGE_SIZE) as usize]);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write(&mut self, adr: u64, bytes: &[u8]) -> Result<(), TrapReason> {
+        let range = self.check_range(adr, bytes.len())?;
+
+        match &mut self.backing {
+            Backing::Flat(memory) => {
+                memory[range.start as usize..range.end as usize].copy_from_slice(bytes)
+            }
+            Backing::Sparse(pages) => {
+                for (offset, byte) in bytes.iter().enumerate() {
+                    let adr = range.start + offset as u64;
+                    let page_index = adr / PAGE_SIZE;
+                    if *byte == 0 && !pages.contains_key(&page_index) {
+                        continue;
+                    }
+
+                    let page = pages
+                        .entry(page_index)
+                        .or_insert_with(|| vec![0; PAGE_SIZE as usize].into_boxed_slice());
+                    page[(adr % PAGE_SIZE) as usize] = *byte;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Range of bytes as an owned copy, useful for inspecting memory from outside
+    // the VM
+    pub fn to_vec(&self, range: Range<u64>) -> Result<Vec<u8>, TrapReason> {
+        let len = range.end.saturating_sub(range.start);
+        let mut bytes = vec![0; len as usize];
+        self.read(range.start, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    // Reads `width` bytes, at most 8, zero extended to a u64
+    pub fn read_uint(&self, adr: u64, width: u64) -> Result<u64, TrapReason> {
+        let mut bytes = [0; 8];
+        self.read(adr, &mut bytes[..width as usize])?;
+        Ok(u64::from_ne_bytes(bytes))
+    }
+
+    // Writes the low `width` bytes of `value`, at most 8
+    pub fn write_uint(&mut self, adr: u64, width: u64, value: u64) -> Result<(), TrapReason> {
+        self.write(adr, &value.to_ne_bytes()[..width as usize])
+    }
+}
@@ -0,0 +1,193 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt, rc::Rc};
+
+// Predicts the direction of conditional control flow, `CJump` and `Branch`. A
+// branch counts as taken when its condition is true, so for `Branch` taken
+// means going to the first target.
+pub trait BranchPredictor: fmt::Debug {
+    fn predict(&mut self, rip: u64) -> bool;
+    fn update(&mut self, rip: u64, taken: bool);
+}
+
+// Predictors are shared with the caller, like devices, so a cloned VM keeps
+// training the same predictor
+pub type SharedPredictor<'a> = Rc<RefCell<dyn BranchPredictor + 'a>>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticTaken;
+
+impl BranchPredictor for StaticTaken {
+    fn predict(&mut self, _rip: u64) -> bool {
+        true
+    }
+
+    fn update(&mut self, _rip: u64, _taken: bool) {}
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StaticNotTaken;
+
+impl BranchPredictor for StaticNotTaken {
+    fn predict(&mut self, _rip: u64) -> bool {
+        false
+    }
+
+    fn update(&mut self, _rip: u64, _taken: bool) {}
+}
+
+// Table based predictors take the number of bits they index their table with.
+// Anything above this would allocate tables of hundreds of megabytes, or
+// overflow the shift computing their size
+pub const MAX_INDEX_BITS: u32 = 24;
+
+fn table_len(name: &str, bits: u32) -> usize {
+    if bits > MAX_INDEX_BITS {
+        panic!(
+            "{} can be at most {} bits, found {}",
+            name, MAX_INDEX_BITS, bits
+        )
+    }
+    1 << bits
+}
+
+// Remembers the last outcome of every branch, indexed by the low bits of RIP
+#[derive(Debug, Clone)]
+pub struct OneBit {
+    table: Vec<bool>,
+}
+
+impl OneBit {
+    pub fn new(index_bits: u32) -> Self {
+        Self {
+            table: vec![false; table_len("index", index_bits)],
+        }
+    }
+}
+
+impl BranchPredictor for OneBit {
+    fn predict(&mut self, rip: u64) -> bool {
+        self.table[rip as usize % self.table.len()]
+    }
+
+    fn update(&mut self, rip: u64, taken: bool) {
+        let index = rip as usize % self.table.len();
+        self.table[index] = taken;
+    }
+}
+
+// 0 and 1 predict not taken, 2 and 3 predict taken. Counters start weakly not
+// taken
+fn predict_counter(counter: u8) -> bool {
+    counter >= 2
+}
+
+fn update_counter(counter: &mut u8, taken: bool) {
+    if taken {
+        *counter = (*counter + 1).min(3);
+    } else {
+        *counter = counter.saturating_sub(1);
+    }
+}
+
+// A table of 2-bit saturating counters indexed by the low bits of RIP
+#[derive(Debug, Clone)]
+pub struct TwoBit {
+    table: Vec<u8>,
+}
+
+impl TwoBit {
+    pub fn new(index_bits: u32) -> Self {
+        Self {
+            table: vec![1; table_len("index", index_bits)],
+        }
+    }
+}
+
+impl BranchPredictor for TwoBit {
+    fn predict(&mut self, rip: u64) -> bool {
+        predict_counter(self.table[rip as usize % self.table.len()])
+    }
+
+    fn update(&mut self, rip: u64, taken: bool) {
+        let index = rip as usize % self.table.len();
+        update_counter(&mut self.table[index], taken);
+    }
+}
+
+// 2-bit saturating counters indexed by RIP xor'd with the global history of
+// the last `history_bits` branch outcomes
+#[derive(Debug, Clone)]
+pub struct Gshare {
+    history: u64,
+    history_bits: u32,
+    table: Vec<u8>,
+}
+
+impl Gshare {
+    pub fn new(history_bits: u32) -> Self {
+        Self {
+            history: 0,
+            history_bits,
+            table: vec![1; table_len("history", history_bits)],
+        }
+    }
+
+    fn index(&self, rip: u64) -> usize {
+        ((rip ^ self.history) as usize) % self.table.len()
+    }
+}
+
+impl BranchPredictor for Gshare {
+    fn predict(&mut self, rip: u64) -> bool {
+        predict_counter(self.table[self.index(rip)])
+    }
+
+    fn update(&mut self, rip: u64, taken: bool) {
+        let index = self.index(rip);
+        update_counter(&mut self.table[index], taken);
+
+        let mask = (1u64 << self.history_bits) - 1;
+        self.history = ((self.history << 1) | taken as u64) & mask;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BranchCounts {
+    pub executed: u64,
+    pub taken: u64,
+    pub mispredicted: u64,
+}
+
+impl BranchCounts {
+    pub fn misprediction_rate(&self) -> f64 {
+        if self.executed == 0 {
+            0.0
+        } else {
+            self.mispredicted as f64 / self.executed as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BranchStats {
+    // Keyed by the instruction index of the branch
+    pub per_branch: BTreeMap<u64, BranchCounts>,
+}
+
+impl BranchStats {
+    pub(crate) fn record(&mut self, rip: u64, taken: bool, predicted: bool) {
+        let counts = self.per_branch.entry(rip).or_default();
+        counts.executed += 1;
+        counts.taken += taken as u64;
+        counts.mispredicted += (taken != predicted) as u64;
+    }
+
+    pub fn overall(&self) -> BranchCounts {
+        self.per_branch
+            .values()
+            .fold(BranchCounts::default(), |total, counts| BranchCounts {
+                executed: total.executed + counts.executed,
+                taken: total.taken + counts.taken,
+                mispredicted: total.mispredicted + counts.mispredicted,
+            })
+    }
+}
@@ -0,0 +1,138 @@
+use super::inst::*;
+use crate::{
+    asm::{self, AsmError, Word},
+    shared::Register,
+};
+
+fn three_reg_inst(mnemonic: &str) -> Option<fn(Register, Register, Register) -> Inst> {
+    Some(match mnemonic {
+        "shl" => Inst::Shl,
+        "shr" => Inst::Shr,
+        "and" => Inst::And,
+        "or" => Inst::Or,
+        "xor" => Inst::Xor,
+
+        "sadd" => Inst::SAdd,
+        "uadd" => Inst::UAdd,
+        "fadd" => Inst::FAdd,
+        "fadd32" => Inst::FAdd32,
+        "sub" => Inst::Sub,
+        "fsub" => Inst::FSub,
+        "fsub32" => Inst::FSub32,
+        "smul" => Inst::SMul,
+        "umul" => Inst::UMul,
+        "fmul" => Inst::FMul,
+        "fmul32" => Inst::FMul32,
+        "sdiv" => Inst::SDiv,
+        "udiv" => Inst::UDiv,
+        "fdiv" => Inst::FDiv,
+        "fdiv32" => Inst::FDiv32,
+        "srem" => Inst::SRem,
+        "urem" => Inst::URem,
+        "frem" => Inst::FRem,
+        "frem32" => Inst::FRem32,
+        "saddchecked" => Inst::SAddChecked,
+        "uaddchecked" => Inst::UAddChecked,
+        "ssubchecked" => Inst::SSubChecked,
+        "usubchecked" => Inst::USubChecked,
+        "smulchecked" => Inst::SMulChecked,
+        "umulchecked" => Inst::UMulChecked,
+        "sdivchecked" => Inst::SDivChecked,
+
+        "eq" => Inst::Eq,
+        "feq" => Inst::FEq,
+        "feq32" => Inst::FEq32,
+        "slt" => Inst::SLt,
+        "ult" => Inst::ULt,
+        "flt" => Inst::FLt,
+        "flt32" => Inst::FLt32,
+        "sgt" => Inst::SGt,
+        "ugt" => Inst::UGt,
+        "fgt" => Inst::FGt,
+        "fgt32" => Inst::FGt32,
+        _ => return None,
+    })
+}
+
+fn two_reg_inst(mnemonic: &str) -> Option<fn(Register, Register) -> Inst> {
+    Some(match mnemonic {
+        "copy" => Inst::Copy,
+        "load" => Inst::Load,
+        "store" => Inst::Store,
+        "load8" => Inst::Load8,
+        "load8s" => Inst::Load8S,
+        "load16" => Inst::Load16,
+        "load16s" => Inst::Load16S,
+        "load32" => Inst::Load32,
+        "load32s" => Inst::Load32S,
+        "store8" => Inst::Store8,
+        "store16" => Inst::Store16,
+        "store32" => Inst::Store32,
+
+        "not" => Inst::Not,
+
+        "stof" => Inst::SToF,
+        "utof" => Inst::UToF,
+        "ftos" => Inst::FToS,
+        "ftou" => Inst::FToU,
+        "ftosround" => Inst::FToSRound,
+        "ftouround" => Inst::FToURound,
+        "f32tof64" => Inst::F32ToF64,
+        "f64tof32" => Inst::F64ToF32,
+        _ => return None,
+    })
+}
+
+fn parse_inst(mnemonic: &Word, operands: &[Word]) -> Result<Inst, AsmError> {
+    if let Some(inst) = three_reg_inst(mnemonic.text) {
+        mnemonic.expect_operands(operands, 3)?;
+        return Ok(inst(
+            operands[0].register()?,
+            operands[1].register()?,
+            operands[2].register()?,
+        ));
+    }
+
+    if let Some(inst) = two_reg_inst(mnemonic.text) {
+        mnemonic.expect_operands(operands, 2)?;
+        return Ok(inst(operands[0].register()?, operands[1].register()?));
+    }
+
+    match mnemonic.text {
+        "syscall" => {
+            mnemonic.expect_operands(operands, 1)?;
+            Ok(Inst::SysCall(operands[0].register()?))
+        }
+
+        "rega" => {
+            mnemonic.expect_operands(operands, 2)?;
+            Ok(Inst::Rega(operands[0].register()?, operands[1].imm()?))
+        }
+
+        "jump" => {
+            mnemonic.expect_operands(operands, 1)?;
+            Ok(Inst::Jump(operands[0].label()?))
+        }
+        "cjump" => {
+            mnemonic.expect_operands(operands, 2)?;
+            Ok(Inst::CJump(operands[0].register()?, operands[1].label()?))
+        }
+        "branch" => {
+            mnemonic.expect_operands(operands, 3)?;
+            Ok(Inst::Branch(
+                operands[0].register()?,
+                operands[1].label()?,
+                operands[2].label()?,
+            ))
+        }
+
+        _ => Err(mnemonic.error_at(&format!("unknown instruction `{}`", mnemonic.text))),
+    }
+}
+
+pub fn parse(source: &str) -> Result<Vec<Block>, AsmError> {
+    Ok(asm::parse_blocks(source, parse_inst)?
+        .into_iter()
+        .map(|(label, insts)| Block { label, insts })
+        .collect())
+}
@@ -0,0 +1,413 @@
+use std::{collections::HashMap, convert::TryInto};
+
+use super::inst::*;
+use crate::{
+    encoding::{fits_signed, DecodeError, EncodeError},
+    shared::{Imm, Label, Register},
+};
+
+// Every instruction is one or more little-endian 32-bit words. The low byte of
+// the first word is the opcode, registers take 5 bits each and jump targets are
+// absolute instruction indices (the same unit `Rip` counts in).
+//
+//   register form  | unused:9 | rs2:5 | rs1:5 | rd:5 | opcode:8 |
+//   rega           |        imm:19   (sign extended) | rd:5 | opcode:8 |
+//   rega.wide      | unused:19 | rd:5 | opcode:8 |  imm[0..32]  |  imm[32..64]  |
+//   jump           |              target:24             | opcode:8 |
+//   cjump          |        target:19          | cond:5 | opcode:8 |
+//   branch         |      true target:19       | cond:5 | opcode:8 |  false target:32  |
+
+const SYSCALL: u8 = 0x00;
+const REGA: u8 = 0x01;
+const REGA_WIDE: u8 = 0x02;
+const COPY: u8 = 0x03;
+const LOAD: u8 = 0x04;
+const STORE: u8 = 0x05;
+
+const JUMP: u8 = 0x08;
+const CJUMP: u8 = 0x09;
+const BRANCH: u8 = 0x0a;
+
+const SHL: u8 = 0x10;
+const SHR: u8 = 0x11;
+const AND: u8 = 0x12;
+const OR: u8 = 0x13;
+const XOR: u8 = 0x14;
+const NOT: u8 = 0x15;
+
+const SADD: u8 = 0x20;
+const UADD: u8 = 0x21;
+const FADD: u8 = 0x22;
+const SUB: u8 = 0x23;
+const FSUB: u8 = 0x24;
+const SMUL: u8 = 0x25;
+const UMUL: u8 = 0x26;
+const FMUL: u8 = 0x27;
+const SDIV: u8 = 0x28;
+const UDIV: u8 = 0x29;
+const FDIV: u8 = 0x2a;
+const SREM: u8 = 0x2b;
+const UREM: u8 = 0x2c;
+const FREM: u8 = 0x2d;
+
+const EQ: u8 = 0x30;
+const FEQ: u8 = 0x31;
+const SLT: u8 = 0x32;
+const ULT: u8 = 0x33;
+const FLT: u8 = 0x34;
+const SGT: u8 = 0x35;
+const UGT: u8 = 0x36;
+const FGT: u8 = 0x37;
+
+const FADD32: u8 = 0x60;
+const FSUB32: u8 = 0x61;
+const FMUL32: u8 = 0x62;
+const FDIV32: u8 = 0x63;
+const FREM32: u8 = 0x64;
+const FEQ32: u8 = 0x68;
+const FLT32: u8 = 0x69;
+const FGT32: u8 = 0x6a;
+
+const SADDCHECKED: u8 = 0x70;
+const UADDCHECKED: u8 = 0x71;
+const SSUBCHECKED: u8 = 0x72;
+const USUBCHECKED: u8 = 0x73;
+const SMULCHECKED: u8 = 0x74;
+const UMULCHECKED: u8 = 0x75;
+const SDIVCHECKED: u8 = 0x76;
+
+const LOAD8: u8 = 0x40;
+const LOAD8S: u8 = 0x41;
+const LOAD16: u8 = 0x42;
+const LOAD16S: u8 = 0x43;
+const LOAD32: u8 = 0x44;
+const LOAD32S: u8 = 0x45;
+const STORE8: u8 = 0x46;
+const STORE16: u8 = 0x47;
+const STORE32: u8 = 0x48;
+
+const STOF: u8 = 0x50;
+const UTOF: u8 = 0x51;
+const FTOS: u8 = 0x52;
+const FTOU: u8 = 0x53;
+const FTOSROUND: u8 = 0x54;
+const FTOUROUND: u8 = 0x55;
+const F32TOF64: u8 = 0x56;
+const F64TOF32: u8 = 0x57;
+
+const REGA_IMM_BITS: u32 = 19;
+const JUMP_TARGET_BITS: u32 = 24;
+const COND_TARGET_BITS: u32 = 19;
+
+fn three_reg_inst(opcode: u8) -> Option<fn(Register, Register, Register) -> Inst> {
+    Some(match opcode {
+        SHL => Inst::Shl,
+        SHR => Inst::Shr,
+        AND => Inst::And,
+        OR => Inst::Or,
+        XOR => Inst::Xor,
+
+        SADD => Inst::SAdd,
+        UADD => Inst::UAdd,
+        FADD => Inst::FAdd,
+        FADD32 => Inst::FAdd32,
+        SUB => Inst::Sub,
+        FSUB => Inst::FSub,
+        FSUB32 => Inst::FSub32,
+        SMUL => Inst::SMul,
+        UMUL => Inst::UMul,
+        FMUL => Inst::FMul,
+        FMUL32 => Inst::FMul32,
+        SDIV => Inst::SDiv,
+        UDIV => Inst::UDiv,
+        FDIV => Inst::FDiv,
+        FDIV32 => Inst::FDiv32,
+        SREM => Inst::SRem,
+        UREM => Inst::URem,
+        FREM => Inst::FRem,
+        FREM32 => Inst::FRem32,
+        SADDCHECKED => Inst::SAddChecked,
+        UADDCHECKED => Inst::UAddChecked,
+        SSUBCHECKED => Inst::SSubChecked,
+        USUBCHECKED => Inst::USubChecked,
+        SMULCHECKED => Inst::SMulChecked,
+        UMULCHECKED => Inst::UMulChecked,
+        SDIVCHECKED => Inst::SDivChecked,
+
+        EQ => Inst::Eq,
+        FEQ => Inst::FEq,
+        FEQ32 => Inst::FEq32,
+        SLT => Inst::SLt,
+        ULT => Inst::ULt,
+        FLT => Inst::FLt,
+        FLT32 => Inst::FLt32,
+        SGT => Inst::SGt,
+        UGT => Inst::UGt,
+        FGT => Inst::FGt,
+        FGT32 => Inst::FGt32,
+        _ => return None,
+    })
+}
+
+fn reg_field(reg: &Register, position: u32) -> u32 {
+    (reg.get_id() as u32) << position
+}
+
+fn register_form(opcode: u8, regs: &[&Register]) -> u32 {
+    regs.iter()
+        .enumerate()
+        .fold(opcode as u32, |word, (i, reg)| {
+            word | reg_field(reg, 8 + 5 * i as u32)
+        })
+}
+
+fn resolve_target(
+    label: &Label,
+    block_table: &HashMap<&str, usize>,
+    bits: u32,
+) -> Result<u32, EncodeError> {
+    let target = if let Some(target) = block_table.get(label.0.as_str()) {
+        *target as u64
+    } else {
+        return Err(EncodeError::UndefinedLabel(label.0.clone()));
+    };
+
+    if target >= 1 << bits {
+        return Err(EncodeError::TargetOutOfRange(label.0.clone(), bits));
+    }
+
+    Ok(target as u32)
+}
+
+fn encode_inst(
+    inst: &Inst,
+    block_table: &HashMap<&str, usize>,
+    words: &mut Vec<u32>,
+) -> Result<(), EncodeError> {
+    match inst {
+        Inst::SysCall(reg) => words.push(register_form(SYSCALL, &[reg])),
+
+        Inst::Rega(dst, imm) => {
+            let value = imm.as_u64();
+            if fits_signed(value, REGA_IMM_BITS) {
+                let imm_field = (value as u32) & ((1 << REGA_IMM_BITS) - 1);
+                words.push(register_form(REGA, &[dst]) | imm_field << 13);
+            } else {
+                words.push(register_form(REGA_WIDE, &[dst]));
+                words.push(value as u32);
+                words.push((value >> 32) as u32);
+            }
+        }
+        Inst::Copy(dst, src) => words.push(register_form(COPY, &[dst, src])),
+        Inst::Load(dst, adr) => words.push(register_form(LOAD, &[dst, adr])),
+        Inst::Store(adr, src) => words.push(register_form(STORE, &[adr, src])),
+        Inst::Load8(lhs, rhs) => words.push(register_form(LOAD8, &[lhs, rhs])),
+        Inst::Load8S(lhs, rhs) => words.push(register_form(LOAD8S, &[lhs, rhs])),
+        Inst::Load16(lhs, rhs) => words.push(register_form(LOAD16, &[lhs, rhs])),
+        Inst::Load16S(lhs, rhs) => words.push(register_form(LOAD16S, &[lhs, rhs])),
+        Inst::Load32(lhs, rhs) => words.push(register_form(LOAD32, &[lhs, rhs])),
+        Inst::Load32S(lhs, rhs) => words.push(register_form(LOAD32S, &[lhs, rhs])),
+        Inst::Store8(lhs, rhs) => words.push(register_form(STORE8, &[lhs, rhs])),
+        Inst::Store16(lhs, rhs) => words.push(register_form(STORE16, &[lhs, rhs])),
+        Inst::Store32(lhs, rhs) => words.push(register_form(STORE32, &[lhs, rhs])),
+
+        Inst::Jump(label) => {
+            let target = resolve_target(label, block_table, JUMP_TARGET_BITS)?;
+            words.push(JUMP as u32 | target << 8);
+        }
+        Inst::CJump(cond, label) => {
+            let target = resolve_target(label, block_table, COND_TARGET_BITS)?;
+            words.push(register_form(CJUMP, &[cond]) | target << 13);
+        }
+        Inst::Branch(cond, true_label, false_label) => {
+            let true_target = resolve_target(true_label, block_table, COND_TARGET_BITS)?;
+            let false_target = resolve_target(false_label, block_table, 32)?;
+            words.push(register_form(BRANCH, &[cond]) | true_target << 13);
+            words.push(false_target);
+        }
+
+        Inst::Shl(dst, lhs, rhs) => words.push(register_form(SHL, &[dst, lhs, rhs])),
+        Inst::Shr(dst, lhs, rhs) => words.push(register_form(SHR, &[dst, lhs, rhs])),
+        Inst::And(dst, lhs, rhs) => words.push(register_form(AND, &[dst, lhs, rhs])),
+        Inst::Or(dst, lhs, rhs) => words.push(register_form(OR, &[dst, lhs, rhs])),
+        Inst::Xor(dst, lhs, rhs) => words.push(register_form(XOR, &[dst, lhs, rhs])),
+        Inst::Not(dst, src) => words.push(register_form(NOT, &[dst, src])),
+
+        Inst::SAdd(dst, lhs, rhs) => words.push(register_form(SADD, &[dst, lhs, rhs])),
+        Inst::UAdd(dst, lhs, rhs) => words.push(register_form(UADD, &[dst, lhs, rhs])),
+        Inst::FAdd(dst, lhs, rhs) => words.push(register_form(FADD, &[dst, lhs, rhs])),
+        Inst::FAdd32(dst, lhs, rhs) => words.push(register_form(FADD32, &[dst, lhs, rhs])),
+        Inst::Sub(dst, lhs, rhs) => words.push(register_form(SUB, &[dst, lhs, rhs])),
+        Inst::FSub(dst, lhs, rhs) => words.push(register_form(FSUB, &[dst, lhs, rhs])),
+        Inst::FSub32(dst, lhs, rhs) => words.push(register_form(FSUB32, &[dst, lhs, rhs])),
+        Inst::SMul(dst, lhs, rhs) => words.push(register_form(SMUL, &[dst, lhs, rhs])),
+        Inst::UMul(dst, lhs, rhs) => words.push(register_form(UMUL, &[dst, lhs, rhs])),
+        Inst::FMul(dst, lhs, rhs) => words.push(register_form(FMUL, &[dst, lhs, rhs])),
+        Inst::FMul32(dst, lhs, rhs) => words.push(register_form(FMUL32, &[dst, lhs, rhs])),
+        Inst::SDiv(dst, lhs, rhs) => words.push(register_form(SDIV, &[dst, lhs, rhs])),
+        Inst::UDiv(dst, lhs, rhs) => words.push(register_form(UDIV, &[dst, lhs, rhs])),
+        Inst::FDiv(dst, lhs, rhs) => words.push(register_form(FDIV, &[dst, lhs, rhs])),
+        Inst::FDiv32(dst, lhs, rhs) => words.push(register_form(FDIV32, &[dst, lhs, rhs])),
+        Inst::SRem(dst, lhs, rhs) => words.push(register_form(SREM, &[dst, lhs, rhs])),
+        Inst::URem(dst, lhs, rhs) => words.push(register_form(UREM, &[dst, lhs, rhs])),
+        Inst::FRem(dst, lhs, rhs) => words.push(register_form(FREM, &[dst, lhs, rhs])),
+        Inst::FRem32(dst, lhs, rhs) => words.push(register_form(FREM32, &[dst, lhs, rhs])),
+        Inst::SAddChecked(dst, lhs, rhs) => {
+            words.push(register_form(SADDCHECKED, &[dst, lhs, rhs]))
+        }
+        Inst::UAddChecked(dst, lhs, rhs) => {
+            words.push(register_form(UADDCHECKED, &[dst, lhs, rhs]))
+        }
+        Inst::SSubChecked(dst, lhs, rhs) => {
+            words.push(register_form(SSUBCHECKED, &[dst, lhs, rhs]))
+        }
+        Inst::USubChecked(dst, lhs, rhs) => {
+            words.push(register_form(USUBCHECKED, &[dst, lhs, rhs]))
+        }
+        Inst::SMulChecked(dst, lhs, rhs) => {
+            words.push(register_form(SMULCHECKED, &[dst, lhs, rhs]))
+        }
+        Inst::UMulChecked(dst, lhs, rhs) => {
+            words.push(register_form(UMULCHECKED, &[dst, lhs, rhs]))
+        }
+        Inst::SDivChecked(dst, lhs, rhs) => {
+            words.push(register_form(SDIVCHECKED, &[dst, lhs, rhs]))
+        }
+
+        Inst::Eq(dst, lhs, rhs) => words.push(register_form(EQ, &[dst, lhs, rhs])),
+        Inst::FEq(dst, lhs, rhs) => words.push(register_form(FEQ, &[dst, lhs, rhs])),
+        Inst::FEq32(dst, lhs, rhs) => words.push(register_form(FEQ32, &[dst, lhs, rhs])),
+        Inst::SLt(dst, lhs, rhs) => words.push(register_form(SLT, &[dst, lhs, rhs])),
+        Inst::ULt(dst, lhs, rhs) => words.push(register_form(ULT, &[dst, lhs, rhs])),
+        Inst::FLt(dst, lhs, rhs) => words.push(register_form(FLT, &[dst, lhs, rhs])),
+        Inst::FLt32(dst, lhs, rhs) => words.push(register_form(FLT32, &[dst, lhs, rhs])),
+        Inst::SGt(dst, lhs, rhs) => words.push(register_form(SGT, &[dst, lhs, rhs])),
+        Inst::UGt(dst, lhs, rhs) => words.push(register_form(UGT, &[dst, lhs, rhs])),
+        Inst::FGt(dst, lhs, rhs) => words.push(register_form(FGT, &[dst, lhs, rhs])),
+        Inst::FGt32(dst, lhs, rhs) => words.push(register_form(FGT32, &[dst, lhs, rhs])),
+
+        Inst::SToF(dst, src) => words.push(register_form(STOF, &[dst, src])),
+        Inst::UToF(dst, src) => words.push(register_form(UTOF, &[dst, src])),
+        Inst::FToS(dst, src) => words.push(register_form(FTOS, &[dst, src])),
+        Inst::FToU(dst, src) => words.push(register_form(FTOU, &[dst, src])),
+        Inst::FToSRound(dst, src) => words.push(register_form(FTOSROUND, &[dst, src])),
+        Inst::FToURound(dst, src) => words.push(register_form(FTOUROUND, &[dst, src])),
+        Inst::F32ToF64(dst, src) => words.push(register_form(F32TOF64, &[dst, src])),
+        Inst::F64ToF32(dst, src) => words.push(register_form(F64TOF32, &[dst, src])),
+    }
+
+    Ok(())
+}
+
+pub fn encode(blocks: &[Block]) -> Result<Vec<u8>, EncodeError> {
+    let mut block_table = HashMap::new();
+    let mut inst_count = 0;
+    for block in blocks {
+        block_table.insert(block.label.as_str(), inst_count);
+        inst_count += block.insts.len();
+    }
+
+    let mut words = Vec::new();
+    for inst in blocks.iter().flat_map(|block| &block.insts) {
+        encode_inst(inst, &block_table, &mut words)?;
+    }
+
+    Ok(words.iter().flat_map(|word| word.to_le_bytes()).collect())
+}
+
+fn decode_reg(word: u32, position: u32, offset: usize) -> Result<Register, DecodeError> {
+    let id = ((word >> position) & 0b11111) as u8;
+    if id > Register::Rip.get_id() {
+        return Err(DecodeError::InvalidRegister(offset, id));
+    }
+
+    Ok(Register::from_id(id))
+}
+
+// Decoded jump targets are named after the instruction index they point at,
+// e.g. `@L12`. Immediates come back as `Imm::Int` holding the original bits.
+pub fn decode(bytes: &[u8]) -> Result<Vec<Inst>, DecodeError> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(DecodeError::UnexpectedEnd(bytes.len() / 4 * 4));
+    }
+
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    let target_label = |target: u32| Label(format!("L{}", target));
+
+    let mut insts = Vec::new();
+    let mut current = 0;
+    while current < words.len() {
+        let word = words[current];
+        let offset = current * 4;
+        current += 1;
+
+        let mut next_word = || {
+            let word = *words
+                .get(current)
+                .ok_or(DecodeError::UnexpectedEnd(current * 4))?;
+            current += 1;
+            Ok(word)
+        };
+        let reg = |position| decode_reg(word, position, offset);
+
+        let opcode = word as u8;
+
+        let inst = if let Some(inst) = three_reg_inst(opcode) {
+            inst(reg(8)?, reg(13)?, reg(18)?)
+        } else {
+            match opcode {
+                SYSCALL => Inst::SysCall(reg(8)?),
+
+                REGA => {
+                    let imm = ((word as i32) >> 13) as i64;
+                    Inst::Rega(reg(8)?, Imm::Int(imm as u64))
+                }
+                REGA_WIDE => {
+                    let low = next_word()? as u64;
+                    let high = next_word()? as u64;
+                    Inst::Rega(reg(8)?, Imm::Int(high << 32 | low))
+                }
+                COPY => Inst::Copy(reg(8)?, reg(13)?),
+                LOAD => Inst::Load(reg(8)?, reg(13)?),
+                STORE => Inst::Store(reg(8)?, reg(13)?),
+                LOAD8 => Inst::Load8(reg(8)?, reg(13)?),
+                LOAD8S => Inst::Load8S(reg(8)?, reg(13)?),
+                LOAD16 => Inst::Load16(reg(8)?, reg(13)?),
+                LOAD16S => Inst::Load16S(reg(8)?, reg(13)?),
+                LOAD32 => Inst::Load32(reg(8)?, reg(13)?),
+                LOAD32S => Inst::Load32S(reg(8)?, reg(13)?),
+                STORE8 => Inst::Store8(reg(8)?, reg(13)?),
+                STORE16 => Inst::Store16(reg(8)?, reg(13)?),
+                STORE32 => Inst::Store32(reg(8)?, reg(13)?),
+
+                JUMP => Inst::Jump(target_label(word >> 8)),
+                CJUMP => Inst::CJump(reg(8)?, target_label(word >> 13)),
+                BRANCH => Inst::Branch(
+                    reg(8)?,
+                    target_label(word >> 13),
+                    target_label(next_word()?),
+                ),
+
+                NOT => Inst::Not(reg(8)?, reg(13)?),
+
+                STOF => Inst::SToF(reg(8)?, reg(13)?),
+                UTOF => Inst::UToF(reg(8)?, reg(13)?),
+                FTOS => Inst::FToS(reg(8)?, reg(13)?),
+                FTOU => Inst::FToU(reg(8)?, reg(13)?),
+                FTOSROUND => Inst::FToSRound(reg(8)?, reg(13)?),
+                FTOUROUND => Inst::FToURound(reg(8)?, reg(13)?),
+                F32TOF64 => Inst::F32ToF64(reg(8)?, reg(13)?),
+                F64TOF32 => Inst::F64ToF32(reg(8)?, reg(13)?),
+
+                _ => return Err(DecodeError::UnknownOpcode(offset, opcode)),
+            }
+        };
+
+        insts.push(inst);
+    }
+
+    Ok(insts)
+}
@@ -1,4 +1,7 @@
-use crate::shared::{Imm, Label, Register};
+use crate::{
+    cost::InstClass,
+    shared::{Imm, Label, Register},
+};
 
 #[derive(Debug, Clone)]
 pub struct Block {
@@ -27,12 +30,24 @@ pub enum Inst {
     Load(Register, Register),
     Store(Register, Register),
 
+    // Sub-word memory accesses, loads either zero (`LoadN`) or sign (`LoadNS`)
+    // extend to 64 bits and stores write the low bytes of the register
+    Load8(Register, Register),
+    Load8S(Register, Register),
+    Load16(Register, Register),
+    Load16S(Register, Register),
+    Load32(Register, Register),
+    Load32S(Register, Register),
+    Store8(Register, Register),
+    Store16(Register, Register),
+    Store32(Register, Register),
+
     // Control flow
     Jump(Label),
     CJump(Register, Label),
     Branch(Register, Label, Label),
 
-    // Bitwise operations
+    // Bitwise operations, shift amounts are taken modulo 64
     Shl(Register, Register, Register),
     Shr(Register, Register, Register),
     And(Register, Register, Register),
@@ -40,40 +55,143 @@ pub enum Inst {
     Xor(Register, Register, Register),
     Not(Register, Register),
 
-    // Arithmetic operations
+    // Arithmetic operations, integer ones wrap around on overflow
     SAdd(Register, Register, Register),
     UAdd(Register, Register, Register),
     FAdd(Register, Register, Register),
+    // The `32` variants of the float instructions work on f32 values held in
+    // the low 32 bits of a register
+    FAdd32(Register, Register, Register),
 
     Sub(Register, Register, Register),
     FSub(Register, Register, Register),
+    FSub32(Register, Register, Register),
 
     SMul(Register, Register, Register),
     UMul(Register, Register, Register),
     FMul(Register, Register, Register),
+    FMul32(Register, Register, Register),
 
     SDiv(Register, Register, Register),
     UDiv(Register, Register, Register),
     FDiv(Register, Register, Register),
+    FDiv32(Register, Register, Register),
 
     SRem(Register, Register, Register),
     URem(Register, Register, Register),
     FRem(Register, Register, Register),
+    FRem32(Register, Register, Register),
+
+    // Like the wrapping instructions but trap on overflow
+    SAddChecked(Register, Register, Register),
+    UAddChecked(Register, Register, Register),
+    SSubChecked(Register, Register, Register),
+    USubChecked(Register, Register, Register),
+    SMulChecked(Register, Register, Register),
+    UMulChecked(Register, Register, Register),
+    SDivChecked(Register, Register, Register),
+
+    // Conversions, `SToF`/`UToF` turn a signed or unsigned integer into an f64
+    // and `FToS`/`FToU` go back by truncating, or by rounding half away from
+    // zero for the `Round` variants. Out of range floats saturate and NaN
+    // becomes 0. f32 values live in the low 32 bits of a register.
+    SToF(Register, Register),
+    UToF(Register, Register),
+    FToS(Register, Register),
+    FToU(Register, Register),
+    FToSRound(Register, Register),
+    FToURound(Register, Register),
+    F32ToF64(Register, Register),
+    F64ToF32(Register, Register),
 
     // Comparative operators
     Eq(Register, Register, Register),
     FEq(Register, Register, Register),
+    FEq32(Register, Register, Register),
 
     SLt(Register, Register, Register),
     ULt(Register, Register, Register),
     FLt(Register, Register, Register),
+    FLt32(Register, Register, Register),
 
     SGt(Register, Register, Register),
     UGt(Register, Register, Register),
     FGt(Register, Register, Register),
+    FGt32(Register, Register, Register),
 }
 
 impl Inst {
+    pub fn class(&self) -> InstClass {
+        match self {
+            Self::SysCall(_) => InstClass::SysCall,
+
+            Self::Rega(_, _) | Self::Copy(_, _) => InstClass::Move,
+            Self::Load(_, _)
+            | Self::Load8(_, _)
+            | Self::Load8S(_, _)
+            | Self::Load16(_, _)
+            | Self::Load16S(_, _)
+            | Self::Load32(_, _)
+            | Self::Load32S(_, _) => InstClass::Load,
+            Self::Store(_, _) | Self::Store8(_, _) | Self::Store16(_, _) | Self::Store32(_, _) => {
+                InstClass::Store
+            }
+
+            Self::Jump(_) => InstClass::Jump,
+            Self::CJump(_, _) | Self::Branch(_, _, _) => InstClass::Branch,
+
+            Self::Shl(_, _, _)
+            | Self::Shr(_, _, _)
+            | Self::And(_, _, _)
+            | Self::Or(_, _, _)
+            | Self::Xor(_, _, _)
+            | Self::Not(_, _)
+            | Self::SAdd(_, _, _)
+            | Self::UAdd(_, _, _)
+            | Self::Sub(_, _, _) => InstClass::IntAlu,
+            Self::SMul(_, _, _) | Self::UMul(_, _, _) => InstClass::IntMul,
+            Self::SDiv(_, _, _)
+            | Self::UDiv(_, _, _)
+            | Self::SRem(_, _, _)
+            | Self::URem(_, _, _) => InstClass::IntDiv,
+            Self::SAddChecked(_, _, _)
+            | Self::UAddChecked(_, _, _)
+            | Self::SSubChecked(_, _, _)
+            | Self::USubChecked(_, _, _) => InstClass::IntAlu,
+            Self::SMulChecked(_, _, _) | Self::UMulChecked(_, _, _) => InstClass::IntMul,
+            Self::SDivChecked(_, _, _) => InstClass::IntDiv,
+            Self::FAdd(_, _, _)
+            | Self::FAdd32(_, _, _)
+            | Self::FSub(_, _, _)
+            | Self::FSub32(_, _, _) => InstClass::FloatAdd,
+            Self::FMul(_, _, _) | Self::FMul32(_, _, _) => InstClass::FloatMul,
+            Self::FDiv(_, _, _)
+            | Self::FDiv32(_, _, _)
+            | Self::FRem(_, _, _)
+            | Self::FRem32(_, _, _) => InstClass::FloatDiv,
+            Self::SToF(_, _)
+            | Self::UToF(_, _)
+            | Self::FToS(_, _)
+            | Self::FToU(_, _)
+            | Self::FToSRound(_, _)
+            | Self::FToURound(_, _)
+            | Self::F32ToF64(_, _)
+            | Self::F64ToF32(_, _) => InstClass::Convert,
+
+            Self::Eq(_, _, _)
+            | Self::FEq(_, _, _)
+            | Self::FEq32(_, _, _)
+            | Self::SLt(_, _, _)
+            | Self::ULt(_, _, _)
+            | Self::FLt(_, _, _)
+            | Self::FLt32(_, _, _)
+            | Self::SGt(_, _, _)
+            | Self::UGt(_, _, _)
+            | Self::FGt(_, _, _)
+            | Self::FGt32(_, _, _) => InstClass::Compare,
+        }
+    }
+
     pub fn as_asm(&self) -> String {
         match self {
             Self::SysCall(reg) => format!("syscall %{}", reg.get_id()),
@@ -82,6 +200,15 @@ impl Inst {
             Self::Copy(dst, src) => format!("copy %{} %{}", dst.get_id(), src.get_id()),
             Self::Load(dst, adr) => format!("load %{} %{}", dst.get_id(), adr.get_id()),
             Self::Store(adr, src) => format!("store %{} %{}", adr.get_id(), src.get_id()),
+            Self::Load8(dst, adr) => format!("load8 %{} %{}", dst.get_id(), adr.get_id()),
+            Self::Load8S(dst, adr) => format!("load8s %{} %{}", dst.get_id(), adr.get_id()),
+            Self::Load16(dst, adr) => format!("load16 %{} %{}", dst.get_id(), adr.get_id()),
+            Self::Load16S(dst, adr) => format!("load16s %{} %{}", dst.get_id(), adr.get_id()),
+            Self::Load32(dst, adr) => format!("load32 %{} %{}", dst.get_id(), adr.get_id()),
+            Self::Load32S(dst, adr) => format!("load32s %{} %{}", dst.get_id(), adr.get_id()),
+            Self::Store8(adr, src) => format!("store8 %{} %{}", adr.get_id(), src.get_id()),
+            Self::Store16(adr, src) => format!("store16 %{} %{}", adr.get_id(), src.get_id()),
+            Self::Store32(adr, src) => format!("store32 %{} %{}", adr.get_id(), src.get_id()),
 
             Self::Jump(label) => format!("jump @{}", &label.0),
             Self::CJump(cond_reg, true_label) => {
@@ -120,6 +247,14 @@ impl Inst {
             Self::FAdd(dst, lhs, rhs) => {
                 format!("fadd %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
+            Self::FAdd32(dst, lhs, rhs) => {
+                format!(
+                    "fadd32 %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
 
             Self::Sub(dst, lhs, rhs) => {
                 format!("sub %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
@@ -127,6 +262,14 @@ impl Inst {
             Self::FSub(dst, lhs, rhs) => {
                 format!("fsub %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
+            Self::FSub32(dst, lhs, rhs) => {
+                format!(
+                    "fsub32 %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
 
             Self::SMul(dst, lhs, rhs) => {
                 format!("smul %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
@@ -137,6 +280,14 @@ impl Inst {
             Self::FMul(dst, lhs, rhs) => {
                 format!("fmul %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
+            Self::FMul32(dst, lhs, rhs) => {
+                format!(
+                    "fmul32 %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
 
             Self::SDiv(dst, lhs, rhs) => {
                 format!("sdiv %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
@@ -147,6 +298,14 @@ impl Inst {
             Self::FDiv(dst, lhs, rhs) => {
                 format!("fdiv %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
+            Self::FDiv32(dst, lhs, rhs) => {
+                format!(
+                    "fdiv32 %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
 
             Self::SRem(dst, lhs, rhs) => {
                 format!("srem %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
@@ -157,6 +316,80 @@ impl Inst {
             Self::FRem(dst, lhs, rhs) => {
                 format!("frem %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
+            Self::FRem32(dst, lhs, rhs) => {
+                format!(
+                    "frem32 %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
+
+            Self::SAddChecked(dst, lhs, rhs) => {
+                format!(
+                    "saddchecked %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
+            Self::UAddChecked(dst, lhs, rhs) => {
+                format!(
+                    "uaddchecked %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
+            Self::SSubChecked(dst, lhs, rhs) => {
+                format!(
+                    "ssubchecked %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
+            Self::USubChecked(dst, lhs, rhs) => {
+                format!(
+                    "usubchecked %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
+            Self::SMulChecked(dst, lhs, rhs) => {
+                format!(
+                    "smulchecked %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
+            Self::UMulChecked(dst, lhs, rhs) => {
+                format!(
+                    "umulchecked %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
+            Self::SDivChecked(dst, lhs, rhs) => {
+                format!(
+                    "sdivchecked %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
+
+            Self::SToF(dst, src) => format!("stof %{} %{}", dst.get_id(), src.get_id()),
+            Self::UToF(dst, src) => format!("utof %{} %{}", dst.get_id(), src.get_id()),
+            Self::FToS(dst, src) => format!("ftos %{} %{}", dst.get_id(), src.get_id()),
+            Self::FToU(dst, src) => format!("ftou %{} %{}", dst.get_id(), src.get_id()),
+            Self::FToSRound(dst, src) => format!("ftosround %{} %{}", dst.get_id(), src.get_id()),
+            Self::FToURound(dst, src) => format!("ftouround %{} %{}", dst.get_id(), src.get_id()),
+            Self::F32ToF64(dst, src) => format!("f32tof64 %{} %{}", dst.get_id(), src.get_id()),
+            Self::F64ToF32(dst, src) => format!("f64tof32 %{} %{}", dst.get_id(), src.get_id()),
 
             Self::Eq(dst, lhs, rhs) => {
                 format!("eq %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
@@ -164,6 +397,14 @@ impl Inst {
             Self::FEq(dst, lhs, rhs) => {
                 format!("feq %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
+            Self::FEq32(dst, lhs, rhs) => {
+                format!(
+                    "feq32 %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
             Self::SLt(dst, lhs, rhs) => {
                 format!("slt %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
@@ -173,6 +414,14 @@ impl Inst {
             Self::FLt(dst, lhs, rhs) => {
                 format!("flt %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
+            Self::FLt32(dst, lhs, rhs) => {
+                format!(
+                    "flt32 %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
             Self::SGt(dst, lhs, rhs) => {
                 format!("sgt %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
@@ -182,6 +431,14 @@ impl Inst {
             Self::FGt(dst, lhs, rhs) => {
                 format!("fgt %{} %{} %{}", dst.get_id(), lhs.get_id(), rhs.get_id())
             }
+            Self::FGt32(dst, lhs, rhs) => {
+                format!(
+                    "fgt32 %{} %{} %{}",
+                    dst.get_id(),
+                    lhs.get_id(),
+                    rhs.get_id()
+                )
+            }
         }
     }
 }
@@ -1,2 +1,5 @@
+pub mod asm;
+pub mod encoding;
 pub mod inst;
+pub mod pipeline;
 pub mod vm;
@@ -0,0 +1,330 @@
+use std::{
+    io::{Read, Write},
+    ops::Range,
+};
+
+use super::{inst::*, vm::VM};
+use crate::{
+    cost::InstClass,
+    shared::{Register, VmError},
+};
+
+// A timing model of a classic in-order 5-stage pipeline. The VM still executes
+// every instruction atomically, the model replays the executed instruction
+// stream and works out the cycle each instruction enters each stage.
+//
+// Branches are predicted not taken and resolve at the end of EX, so every
+// control transfer that doesn't fall through flushes the two younger
+// instructions. Without forwarding a register is read in ID during the cycle
+// its producer is in WB.
+
+const IF: usize = 0;
+const ID: usize = 1;
+const EX: usize = 2;
+const MEM: usize = 3;
+const WB: usize = 4;
+
+const STAGE_NAMES: [&str; 5] = ["IF", "ID", "EX", "MEM", "WB"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineConfig {
+    pub forwarding: bool,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self { forwarding: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HazardKind {
+    // `forwarded` is set when the value was bypassed to EX instead of being
+    // read from the register file
+    Data { reg: Register, forwarded: bool },
+    Control,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hazard {
+    // Position in the executed instruction stream, not the instruction index
+    pub seq: usize,
+    pub rip: u64,
+    pub kind: HazardKind,
+    pub stall_cycles: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstTiming {
+    pub rip: u64,
+    pub asm: String,
+    // Cycle each stage was entered, in IF, ID, EX, MEM, WB order
+    pub stages: [u64; 5],
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PipelineReport {
+    pub cycles: u64,
+    pub data_stall_cycles: u64,
+    pub control_stall_cycles: u64,
+    pub timings: Vec<InstTiming>,
+    pub hazards: Vec<Hazard>,
+}
+
+impl PipelineReport {
+    pub fn insts(&self) -> usize {
+        self.timings.len()
+    }
+
+    pub fn cpi(&self) -> f64 {
+        if self.timings.is_empty() {
+            0.0
+        } else {
+            self.cycles as f64 / self.timings.len() as f64
+        }
+    }
+
+    // One row per executed instruction in `range` (positions in the executed
+    // stream) and one column per cycle, a stalled instruction repeats the
+    // stage it's stuck in
+    pub fn diagram(&self, range: Range<usize>) -> String {
+        let timings = match self.timings.get(range) {
+            Some(timings) if !timings.is_empty() => timings,
+            _ => return String::new(),
+        };
+
+        let first_cycle = timings[0].stages[IF];
+        let last_cycle = timings
+            .iter()
+            .map(|timing| timing.stages[WB])
+            .max()
+            .unwrap();
+
+        let mut result = format!("{:<36}", "cycle");
+        for cycle in first_cycle..=last_cycle {
+            result += &format!("{:<4}", cycle);
+        }
+        result = result.trim_end().to_string() + "\n";
+
+        for timing in timings {
+            let mut row = format!("{:>6}: {:<28}", timing.rip, timing.asm);
+            for cycle in first_cycle..=last_cycle {
+                let stage = (IF..=WB).rev().find(|stage| timing.stages[*stage] <= cycle);
+                let cell = match stage {
+                    Some(WB) if cycle > timing.stages[WB] => "",
+                    Some(stage) => STAGE_NAMES[stage],
+                    None => "",
+                };
+                row += &format!("{:<4}", cell);
+            }
+            result += row.trim_end();
+            result.push('\n');
+        }
+
+        result
+    }
+}
+
+// Returns the register written and the registers read, `Rip` is left out since
+// writes to it are control hazards and reading it never stalls
+fn dependencies(inst: &Inst) -> (Option<Register>, Vec<Register>) {
+    let (dst, srcs) = match inst {
+        // Syscalls take their argument in R1 and return their result in it
+        Inst::SysCall(reg) => (Some(Register::R1), vec![*reg, Register::R1]),
+
+        Inst::Rega(dst, _) => (Some(*dst), vec![]),
+        Inst::Copy(dst, src)
+        | Inst::Load(dst, src)
+        | Inst::Load8(dst, src)
+        | Inst::Load8S(dst, src)
+        | Inst::Load16(dst, src)
+        | Inst::Load16S(dst, src)
+        | Inst::Load32(dst, src)
+        | Inst::Load32S(dst, src)
+        | Inst::Not(dst, src)
+        | Inst::SToF(dst, src)
+        | Inst::UToF(dst, src)
+        | Inst::FToS(dst, src)
+        | Inst::FToU(dst, src)
+        | Inst::FToSRound(dst, src)
+        | Inst::FToURound(dst, src)
+        | Inst::F32ToF64(dst, src)
+        | Inst::F64ToF32(dst, src) => (Some(*dst), vec![*src]),
+        Inst::Store(adr, src)
+        | Inst::Store8(adr, src)
+        | Inst::Store16(adr, src)
+        | Inst::Store32(adr, src) => (None, vec![*adr, *src]),
+
+        Inst::Jump(_) => (None, vec![]),
+        Inst::CJump(cond, _) | Inst::Branch(cond, _, _) => (None, vec![*cond]),
+
+        Inst::Shl(dst, lhs, rhs)
+        | Inst::Shr(dst, lhs, rhs)
+        | Inst::And(dst, lhs, rhs)
+        | Inst::Or(dst, lhs, rhs)
+        | Inst::Xor(dst, lhs, rhs)
+        | Inst::SAdd(dst, lhs, rhs)
+        | Inst::UAdd(dst, lhs, rhs)
+        | Inst::FAdd(dst, lhs, rhs)
+        | Inst::FAdd32(dst, lhs, rhs)
+        | Inst::Sub(dst, lhs, rhs)
+        | Inst::FSub(dst, lhs, rhs)
+        | Inst::FSub32(dst, lhs, rhs)
+        | Inst::SMul(dst, lhs, rhs)
+        | Inst::UMul(dst, lhs, rhs)
+        | Inst::FMul(dst, lhs, rhs)
+        | Inst::FMul32(dst, lhs, rhs)
+        | Inst::SDiv(dst, lhs, rhs)
+        | Inst::UDiv(dst, lhs, rhs)
+        | Inst::FDiv(dst, lhs, rhs)
+        | Inst::FDiv32(dst, lhs, rhs)
+        | Inst::SRem(dst, lhs, rhs)
+        | Inst::URem(dst, lhs, rhs)
+        | Inst::FRem(dst, lhs, rhs)
+        | Inst::FRem32(dst, lhs, rhs)
+        | Inst::SAddChecked(dst, lhs, rhs)
+        | Inst::UAddChecked(dst, lhs, rhs)
+        | Inst::SSubChecked(dst, lhs, rhs)
+        | Inst::USubChecked(dst, lhs, rhs)
+        | Inst::SMulChecked(dst, lhs, rhs)
+        | Inst::UMulChecked(dst, lhs, rhs)
+        | Inst::SDivChecked(dst, lhs, rhs)
+        | Inst::Eq(dst, lhs, rhs)
+        | Inst::FEq(dst, lhs, rhs)
+        | Inst::FEq32(dst, lhs, rhs)
+        | Inst::SLt(dst, lhs, rhs)
+        | Inst::ULt(dst, lhs, rhs)
+        | Inst::FLt(dst, lhs, rhs)
+        | Inst::FLt32(dst, lhs, rhs)
+        | Inst::SGt(dst, lhs, rhs)
+        | Inst::UGt(dst, lhs, rhs)
+        | Inst::FGt(dst, lhs, rhs)
+        | Inst::FGt32(dst, lhs, rhs) => (Some(*dst), vec![*lhs, *rhs]),
+    };
+
+    (
+        dst.filter(|reg| *reg != Register::Rip),
+        srcs.into_iter()
+            .filter(|reg| *reg != Register::Rip)
+            .collect(),
+    )
+}
+
+// Runs the VM to completion, ignoring breakpoints and watchpoints, and reports
+// how the executed instructions flowed through the pipeline
+pub fn simulate<R: Read, W: Write>(
+    vm: &mut VM<'_, R, W>,
+    config: PipelineConfig,
+) -> Result<PipelineReport, VmError> {
+    let mut report = PipelineReport::default();
+
+    // The executed instruction that last wrote each register and whether it
+    // was a load
+    let mut producers: [Option<(usize, bool)>; 19] = [None; 19];
+    // Earliest cycle the next instruction can be fetched after a taken branch,
+    // along with the branch
+    let mut redirect: Option<(u64, usize)> = None;
+
+    while !vm.is_halted() {
+        let rip = vm.rip();
+        let inst = match vm.current_inst() {
+            Some(inst) => inst.clone(),
+            // Let the VM report the out of bounds RIP
+            None => {
+                vm.step()?;
+                continue;
+            }
+        };
+
+        vm.step()?;
+
+        let seq = report.timings.len();
+        let prev = report.timings.last().map(|timing| timing.stages);
+        let (dst, srcs) = dependencies(&inst);
+
+        // An instruction can only enter a stage once the one ahead has left it
+        let after_prev = |stage: usize, earliest: u64| match prev {
+            Some(prev) if stage < WB => earliest.max(prev[stage + 1]),
+            Some(prev) => earliest.max(prev[WB] + 1),
+            None => earliest,
+        };
+
+        let mut stages = [0; 5];
+
+        let fetch = after_prev(IF, prev.map_or(0, |prev| prev[IF] + 1));
+        stages[IF] = fetch;
+        if let Some((resolved, branch_seq)) = redirect.take() {
+            stages[IF] = fetch.max(resolved);
+            report.control_stall_cycles += stages[IF] - fetch;
+            report.hazards.push(Hazard {
+                seq: branch_seq,
+                rip: report.timings[branch_seq].rip,
+                kind: HazardKind::Control,
+                stall_cycles: stages[IF] - fetch,
+            });
+        }
+
+        stages[ID] = after_prev(ID, stages[IF] + 1);
+
+        // Registers are read in the last cycle spent in ID. A value still in
+        // flight either comes through the bypass network or holds the
+        // instruction in ID until its producer reaches WB
+        let execute = after_prev(EX, stages[ID] + 1);
+        stages[EX] = execute;
+        for src in &srcs {
+            let (producer, is_load) = match producers[src.get_id() as usize] {
+                Some(producer) => producer,
+                None => continue,
+            };
+            let producer_timing = &report.timings[producer];
+            if producer_timing.stages[WB] < execute {
+                continue;
+            }
+
+            // Loaded values are only available after MEM
+            let ready = if !config.forwarding {
+                producer_timing.stages[WB] + 1
+            } else if is_load {
+                producer_timing.stages[MEM] + 1
+            } else {
+                producer_timing.stages[EX] + 1
+            };
+            report.hazards.push(Hazard {
+                seq,
+                rip,
+                kind: HazardKind::Data {
+                    reg: *src,
+                    forwarded: config.forwarding,
+                },
+                stall_cycles: ready.saturating_sub(execute),
+            });
+            stages[EX] = stages[EX].max(ready);
+        }
+        report.data_stall_cycles += stages[EX] - execute;
+
+        stages[MEM] = after_prev(MEM, stages[EX] + 1);
+        stages[WB] = after_prev(WB, stages[MEM] + 1);
+
+        if let Some(dst) = dst {
+            producers[dst.get_id() as usize] = Some((seq, inst.class() == InstClass::Load));
+        }
+
+        // Predicted not taken, anything else is fetched once EX resolves it
+        if !vm.is_halted() && vm.rip() != rip.wrapping_add(1) {
+            redirect = Some((stages[EX] + 1, seq));
+        }
+
+        report.timings.push(InstTiming {
+            rip,
+            asm: inst.as_asm(),
+            stages,
+        });
+    }
+
+    report.cycles = report
+        .timings
+        .last()
+        .map_or(0, |timing| timing.stages[WB] + 1);
+
+    Ok(report)
+}
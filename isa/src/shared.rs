@@ -1,3 +1,7 @@
+use std::{error::Error, fmt, ops::Range};
+
+use crate::{cache::CacheStats, cost::PerfCounters, predictor::BranchStats};
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd)]
 pub enum Register {
@@ -95,7 +99,7 @@ pub enum Imm {
 impl Imm {
     pub fn as_u64(&self) -> u64 {
         match self {
-            Self::Int(int_value) => *int_value as u64,
+            Self::Int(int_value) => *int_value,
             Self::Float(float_value) => float_value.to_bits(),
             Self::True => u64::MAX,
             Self::False => 0,
@@ -105,14 +109,16 @@ impl Imm {
     pub fn as_asm(&self) -> String {
         match self {
             Self::Int(i) => i.to_string(),
-            Self::Float(f) => f.to_string(),
+            // Debug formatting keeps the fractional part (`2.0` rather than `2`)
+            // so the value reads back as a float
+            Self::Float(f) => format!("{:?}", f),
             Self::True => "true".to_string(),
             Self::False => "false".to_string(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Registers {
     r0: u64,
     r1: u64,
@@ -214,3 +220,205 @@ impl Default for Registers {
         Self::new()
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrapReason {
+    UndefinedLabel(String),
+    UnknownSyscall(u64),
+    MemoryOutOfBounds(u64),
+    DivisionByZero,
+    // Raised by the checked arithmetic instructions
+    Overflow,
+    ImmediateDestination,
+    RipOutOfBounds(u64),
+    OutputFailed,
+    // Reading failed or the input ended
+    InputFailed,
+    InvalidInput(String),
+    // A syscall read an argument past the last register arguments are passed in
+    InvalidSyscallArg(u8),
+}
+
+impl fmt::Display for TrapReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndefinedLabel(label) => write!(f, "undefined label `{}`", label),
+            Self::UnknownSyscall(id) => write!(f, "unknown syscall ID: {}", id),
+            Self::MemoryOutOfBounds(adr) => write!(f, "memory access out of bounds at {}", adr),
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::Overflow => write!(f, "arithmetic overflow"),
+            Self::ImmediateDestination => write!(f, "destination cannot be an immediate value"),
+            Self::RipOutOfBounds(rip) => {
+                write!(f, "instruction pointer {} is outside of the program", rip)
+            }
+            Self::OutputFailed => write!(f, "failed to write program output"),
+            Self::InputFailed => write!(f, "failed to read program input"),
+            Self::InvalidInput(input) => write!(f, "invalid program input `{}`", input),
+            Self::InvalidSyscallArg(index) => {
+                write!(f, "no register holds syscall argument {}", index)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmError {
+    pub inst_index: u64,
+    pub registers: Box<Registers>,
+    pub reason: TrapReason,
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "trap at instruction {}: {}",
+            self.inst_index, self.reason
+        )
+    }
+}
+
+impl Error for VmError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HaltReason {
+    // `main` returned or the program set `Rip` to `u64::MAX`
+    Returned,
+    // The program called the `exit` syscall
+    Exited,
+    Trapped(VmError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitStatus {
+    // 0 when the program returned, 1 when it trapped, otherwise the code passed
+    // to `exit`
+    pub code: i64,
+    pub reason: HaltReason,
+    pub perf: PerfCounters,
+    // Only set when a cache was attached to the VM
+    pub cache: Option<CacheStats>,
+    // Only set when a branch predictor was attached to the VM
+    pub branches: Option<BranchStats>,
+}
+
+impl ExitStatus {
+    pub fn insts(&self) -> u64 {
+        self.perf.insts_retired
+    }
+
+    pub fn trap(&self) -> Option<&VmError> {
+        match &self.reason {
+            HaltReason::Trapped(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// Where a breakpoint sits, either the start of a labelled block or an absolute
+// instruction index (the same unit `Rip` counts in)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    Label(String),
+    Index(u64),
+}
+
+impl From<&str> for Location {
+    fn from(label: &str) -> Self {
+        Self::Label(label.to_string())
+    }
+}
+
+impl From<u64> for Location {
+    fn from(index: u64) -> Self {
+        Self::Index(index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Halted(ExitStatus),
+    // Execution stopped before running the instruction at this index
+    Breakpoint(u64),
+    // Execution stopped after the instruction that touched watched memory
+    Watchpoint(WatchpointHit),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+// Watches a range of memory addresses, any access overlapping it triggers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: Range<u64>,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    pub fn triggers(&self, adr: u64, size: u64, access: Access) -> bool {
+        let kind_matches = matches!(
+            (self.kind, access),
+            (WatchKind::ReadWrite, _)
+                | (WatchKind::Read, Access::Read)
+                | (WatchKind::Write, Access::Write)
+        );
+
+        kind_matches && adr < self.range.end && adr.saturating_add(size) > self.range.start
+    }
+}
+
+// For reads the old and new values are both the value that was read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchpointHit {
+    pub inst_index: u64,
+    pub adr: u64,
+    pub size: u64,
+    pub access: Access,
+    pub old_value: u64,
+    pub new_value: u64,
+}
+
+// A single memory access of `size` bytes made by an instruction, for reads the
+// old and new values are both the value that was read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub adr: u64,
+    pub size: u64,
+    pub access: Access,
+    pub old_value: u64,
+    pub new_value: u64,
+}
+
+// What a single executed instruction overwrote, enough to step back over it
+#[derive(Debug, Clone)]
+pub(crate) struct UndoEntry {
+    pub registers: Registers,
+    pub perf: PerfCounters,
+    // (address, size, previous value) of every memory write, in execution order
+    pub memory_writes: Vec<(u64, u64, u64)>,
+}
+
+// Zero extends the low `width` bytes of `value` to 64 bits
+pub fn zero_extend(value: u64, width: u64) -> u64 {
+    if width >= 8 {
+        value
+    } else {
+        value & ((1 << (width * 8)) - 1)
+    }
+}
+
+// Sign extends the low `width` bytes of `value` to 64 bits
+pub fn sign_extend(value: u64, width: u64) -> u64 {
+    let shift = 64 - width * 8;
+    (((value << shift) as i64) >> shift) as u64
+}
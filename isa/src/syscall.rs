@@ -0,0 +1,309 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    io::{BufRead, SeekFrom, Write},
+    rc::Rc,
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{
+    clock::Clock,
+    fs::{OpenMode, VirtualFs},
+    memory::Memory,
+    shared::{Register, Registers, TrapReason},
+};
+
+// R15 is the last general purpose register
+const MAX_ARG_REGISTER: u8 = 15;
+
+// What a syscall gets to see of the VM that executed it. Arguments are passed
+// in R1 to R15 and results are returned in R1.
+pub struct SyscallContext<'s> {
+    pub registers: &'s mut Registers,
+    // Read only, `write_memory` is how a syscall writes to memory
+    pub memory: &'s Memory,
+    pub writer: &'s mut dyn Write,
+    pub reader: &'s mut dyn BufRead,
+    pub clock: &'s mut Clock,
+    pub fs: &'s mut VirtualFs,
+    pub(crate) exit_code: Option<i64>,
+    pub(crate) memory_writes: Vec<(u64, Vec<u8>)>,
+}
+
+impl SyscallContext<'_> {
+    // Argument `index` counts from 0, there's one for each of R1 to R15
+    pub fn arg(&self, index: u8) -> Result<u64, TrapReason> {
+        match index.checked_add(1) {
+            Some(id) if id <= MAX_ARG_REGISTER => Ok(self.registers.get(&Register::from_id(id))),
+            _ => Err(TrapReason::InvalidSyscallArg(index)),
+        }
+    }
+
+    pub fn set_result(&mut self, value: u64) {
+        self.registers.set(&Register::R1, value);
+    }
+
+    // Halts the VM once the handler returns
+    pub fn exit(&mut self, code: i64) {
+        self.exit_code = Some(code);
+    }
+
+    // Writes `bytes` once the handler returns, the way store instructions
+    // would, so watchpoints, history, the cache and mapped devices see them
+    pub fn write_memory(&mut self, adr: u64, bytes: &[u8]) {
+        self.memory_writes.push((adr, bytes.to_vec()));
+    }
+}
+
+pub trait SyscallHandler {
+    fn handle(&mut self, ctx: &mut SyscallContext<'_>) -> Result<(), TrapReason>;
+}
+
+impl<F> SyscallHandler for F
+where
+    F: FnMut(&mut SyscallContext<'_>) -> Result<(), TrapReason>,
+{
+    fn handle(&mut self, ctx: &mut SyscallContext<'_>) -> Result<(), TrapReason> {
+        self(ctx)
+    }
+}
+
+pub const PRINT_U64: u64 = 0;
+pub const PRINT_I64: u64 = 1;
+pub const PRINT_F32: u64 = 2;
+pub const PRINT_F64: u64 = 3;
+pub const PRINT_BOOL: u64 = 4;
+pub const SLEEP: u64 = 5;
+pub const READ_U64: u64 = 6;
+pub const READ_I64: u64 = 7;
+pub const READ_F64: u64 = 8;
+pub const READ_BOOL: u64 = 9;
+pub const READ_LINE: u64 = 10;
+pub const TIME_NOW: u64 = 11;
+pub const EXIT: u64 = 12;
+pub const OPEN: u64 = 13;
+pub const READ: u64 = 14;
+pub const WRITE: u64 = 15;
+pub const SEEK: u64 = 16;
+pub const CLOSE: u64 = 17;
+
+// Returned by the file syscalls when the operation failed
+pub const FS_ERROR: u64 = u64::MAX;
+
+fn print(ctx: &mut SyscallContext<'_>, value: impl fmt::Display) -> Result<(), TrapReason> {
+    writeln!(ctx.writer, "{}", value).map_err(|_| TrapReason::OutputFailed)
+}
+
+// Reads up to and including the next newline, which is stripped. Returns `None`
+// once the input has ended
+fn read_line(ctx: &mut SyscallContext<'_>) -> Result<Option<Vec<u8>>, TrapReason> {
+    let mut line = Vec::new();
+    let read = ctx
+        .reader
+        .read_until(b'\n', &mut line)
+        .map_err(|_| TrapReason::InputFailed)?;
+    if read == 0 {
+        return Ok(None);
+    }
+
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+// Values are read one per line, surrounding whitespace is ignored
+fn read_value<T: FromStr>(ctx: &mut SyscallContext<'_>) -> Result<T, TrapReason> {
+    let line = read_line(ctx)?.ok_or(TrapReason::InputFailed)?;
+    let line = String::from_utf8_lossy(&line);
+    line.trim()
+        .parse()
+        .map_err(|_| TrapReason::InvalidInput(line.trim().to_string()))
+}
+
+// The most bytes a single file syscall copies out of or into memory
+pub const MAX_TRANSFER: u64 = 1 << 20;
+
+// Copies a range of memory a file syscall works on. The length comes straight
+// from the program and sparse memory can be far larger than the host's, so it's
+// capped at `MAX_TRANSFER`, returning `None` above it, before allocating
+fn memory_range(
+    ctx: &SyscallContext<'_>,
+    adr: u64,
+    len: u64,
+) -> Result<Option<Vec<u8>>, TrapReason> {
+    match adr.checked_add(len) {
+        Some(end) if end <= ctx.memory.size() => {
+            if len > MAX_TRANSFER {
+                return Ok(None);
+            }
+            ctx.memory.to_vec(adr..end).map(Some)
+        }
+        _ => Err(TrapReason::MemoryOutOfBounds(adr)),
+    }
+}
+
+fn fs_result(ctx: &mut SyscallContext<'_>, result: Option<u64>) -> Result<(), TrapReason> {
+    ctx.set_result(result.unwrap_or(FS_ERROR));
+    Ok(())
+}
+
+// The handlers a VM dispatches `syscall` to, keyed by ID. Cloning shares the
+// handlers rather than copying them.
+#[derive(Clone)]
+pub struct Syscalls<'a> {
+    handlers: HashMap<u64, Rc<RefCell<dyn SyscallHandler + 'a>>>,
+}
+
+impl fmt::Debug for Syscalls<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids = self.handlers.keys().collect::<Vec<_>>();
+        ids.sort();
+        f.debug_struct("Syscalls").field("ids", &ids).finish()
+    }
+}
+
+impl Default for Syscalls<'_> {
+    // The table documented in docs/syscall.md
+    fn default() -> Self {
+        let mut syscalls = Self::empty();
+        syscalls.register(PRINT_U64, |ctx: &mut SyscallContext<'_>| {
+            print(ctx, ctx.arg(0)?)
+        });
+        syscalls.register(PRINT_I64, |ctx: &mut SyscallContext<'_>| {
+            print(ctx, ctx.arg(0)? as i64)
+        });
+        syscalls.register(PRINT_F32, |ctx: &mut SyscallContext<'_>| {
+            print(ctx, f32::from_bits(ctx.arg(0)? as u32))
+        });
+        syscalls.register(PRINT_F64, |ctx: &mut SyscallContext<'_>| {
+            print(ctx, f64::from_bits(ctx.arg(0)?))
+        });
+        syscalls.register(PRINT_BOOL, |ctx: &mut SyscallContext<'_>| {
+            print(ctx, ctx.arg(0)? != 0)
+        });
+        syscalls.register(SLEEP, |ctx: &mut SyscallContext<'_>| {
+            ctx.clock.sleep(Duration::from_millis(ctx.arg(0)?));
+            Ok(())
+        });
+        syscalls.register(READ_U64, |ctx: &mut SyscallContext<'_>| {
+            let value = read_value::<u64>(ctx)?;
+            ctx.set_result(value);
+            Ok(())
+        });
+        syscalls.register(READ_I64, |ctx: &mut SyscallContext<'_>| {
+            let value = read_value::<i64>(ctx)?;
+            ctx.set_result(value as u64);
+            Ok(())
+        });
+        syscalls.register(READ_F64, |ctx: &mut SyscallContext<'_>| {
+            let value = read_value::<f64>(ctx)?;
+            ctx.set_result(value.to_bits());
+            Ok(())
+        });
+        syscalls.register(READ_BOOL, |ctx: &mut SyscallContext<'_>| {
+            // Same representation as the `true` immediate
+            let value = read_value::<bool>(ctx)?;
+            ctx.set_result(if value { u64::MAX } else { 0 });
+            Ok(())
+        });
+        // Stores at most `r2` bytes of the line at the address in `r1`, the rest
+        // of a longer line is discarded
+        syscalls.register(READ_LINE, |ctx: &mut SyscallContext<'_>| {
+            let (adr, capacity) = (ctx.arg(0)?, ctx.arg(1)?);
+            let mut line = read_line(ctx)?.unwrap_or_default();
+            line.truncate(capacity.min(line.len() as u64) as usize);
+            ctx.write_memory(adr, &line);
+            ctx.set_result(line.len() as u64);
+            Ok(())
+        });
+        syscalls.register(TIME_NOW, |ctx: &mut SyscallContext<'_>| {
+            let now = ctx.clock.now_ns();
+            ctx.set_result(now);
+            Ok(())
+        });
+        syscalls.register(EXIT, |ctx: &mut SyscallContext<'_>| {
+            ctx.exit(ctx.arg(0)? as i64);
+            Ok(())
+        });
+        // The path is `r2` bytes at the address in `r1`, `r3` is the mode
+        syscalls.register(OPEN, |ctx: &mut SyscallContext<'_>| {
+            let path = match memory_range(ctx, ctx.arg(0)?, ctx.arg(1)?)? {
+                Some(path) => path,
+                None => return fs_result(ctx, None),
+            };
+            let path = String::from_utf8_lossy(&path);
+            let fd = OpenMode::from_id(ctx.arg(2)?).and_then(|mode| ctx.fs.open(&path, mode));
+            fs_result(ctx, fd)
+        });
+        // Reads at most `r3` bytes from the file in `r1` to the address in `r2`
+        syscalls.register(READ, |ctx: &mut SyscallContext<'_>| {
+            let mut buf = match memory_range(ctx, ctx.arg(1)?, ctx.arg(2)?)? {
+                Some(buf) => buf,
+                None => return fs_result(ctx, None),
+            };
+            let read = ctx.fs.read(ctx.arg(0)?, &mut buf);
+            if let Some(len) = read {
+                ctx.write_memory(ctx.arg(1)?, &buf[..len]);
+            }
+            fs_result(ctx, read.map(|len| len as u64))
+        });
+        // Writes `r3` bytes from the address in `r2` to the file in `r1`
+        syscalls.register(WRITE, |ctx: &mut SyscallContext<'_>| {
+            let bytes = match memory_range(ctx, ctx.arg(1)?, ctx.arg(2)?)? {
+                Some(bytes) => bytes,
+                None => return fs_result(ctx, None),
+            };
+            let written = ctx.fs.write(ctx.arg(0)?, &bytes);
+            fs_result(ctx, written.map(|len| len as u64))
+        });
+        // Moves to the signed offset in `r2` from the start (`r3` = 0), the
+        // current position (1) or the end (2)
+        syscalls.register(SEEK, |ctx: &mut SyscallContext<'_>| {
+            let offset = ctx.arg(1)?;
+            let pos = match ctx.arg(2)? {
+                0 => Some(SeekFrom::Start(offset)),
+                1 => Some(SeekFrom::Current(offset as i64)),
+                2 => Some(SeekFrom::End(offset as i64)),
+                _ => None,
+            };
+            let fd = ctx.arg(0)?;
+            let new_pos = pos.and_then(|pos| ctx.fs.seek(fd, pos));
+            fs_result(ctx, new_pos)
+        });
+        syscalls.register(CLOSE, |ctx: &mut SyscallContext<'_>| {
+            let closed = ctx.fs.close(ctx.arg(0)?);
+            fs_result(ctx, closed.map(|_| 0))
+        });
+        syscalls
+    }
+}
+
+impl<'a> Syscalls<'a> {
+    pub fn empty() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    // Replaces any handler already registered for `id`
+    pub fn register(&mut self, id: u64, handler: impl SyscallHandler + 'a) {
+        self.handlers.insert(id, Rc::new(RefCell::new(handler)));
+    }
+
+    pub fn unregister(&mut self, id: u64) {
+        self.handlers.remove(&id);
+    }
+
+    pub fn dispatch(&mut self, id: u64, ctx: &mut SyscallContext<'_>) -> Result<(), TrapReason> {
+        match self.handlers.get(&id) {
+            Some(handler) => handler.borrow_mut().handle(ctx),
+            None => Err(TrapReason::UnknownSyscall(id)),
+        }
+    }
+}
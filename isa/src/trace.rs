@@ -0,0 +1,132 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use crate::shared::{Access, MemoryAccess, Register, Registers};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    Text,
+    JsonLines,
+}
+
+// Writes one line per executed instruction: its RIP and assembly, the registers
+// it changed and the memory it touched. Clones write to the same sink.
+#[derive(Clone)]
+pub struct Tracer<'a> {
+    writer: Rc<RefCell<dyn Write + 'a>>,
+    format: TraceFormat,
+}
+
+impl<'a> fmt::Debug for Tracer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+fn access_name(access: Access) -> &'static str {
+    match access {
+        Access::Read => "read",
+        Access::Write => "write",
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result += "\\\"",
+            '\\' => result += "\\\\",
+            c if c.is_control() => result += &format!("\\u{:04x}", c as u32),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+impl<'a> Tracer<'a> {
+    pub fn new(writer: impl Write + 'a, format: TraceFormat) -> Self {
+        Self {
+            writer: Rc::new(RefCell::new(writer)),
+            format,
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        rip: u64,
+        asm: &str,
+        before: &Registers,
+        after: &Registers,
+        accesses: &[MemoryAccess],
+    ) -> io::Result<()> {
+        // RIP moves on every instruction so it's only reported as the location
+        let written: Vec<(Register, u64, u64)> = (0..Register::Rip.get_id())
+            .map(Register::from_id)
+            .filter(|reg| before.get(reg) != after.get(reg))
+            .map(|reg| (reg, before.get(&reg), after.get(&reg)))
+            .collect();
+
+        match self.format {
+            TraceFormat::Text => {
+                let mut effects = Vec::new();
+                for (reg, _, new_value) in &written {
+                    effects.push(format!("%{} = {:#x}", reg.get_id(), new_value));
+                }
+                for access in accesses {
+                    effects.push(match access.access {
+                        Access::Read => format!("read [{:#x}] {:#x}", access.adr, access.new_value),
+                        Access::Write => format!(
+                            "write [{:#x}] {:#x} -> {:#x}",
+                            access.adr, access.old_value, access.new_value
+                        ),
+                    });
+                }
+
+                let line = format!("{:>6}: {:<28}{}", rip, asm, effects.join(", "));
+                writeln!(self.writer.borrow_mut(), "{}", line.trim_end())
+            }
+            TraceFormat::JsonLines => {
+                let registers: Vec<String> = written
+                    .iter()
+                    .map(|(reg, old_value, new_value)| {
+                        format!(
+                            "{{\"reg\":{},\"old\":{},\"new\":{}}}",
+                            reg.get_id(),
+                            old_value,
+                            new_value
+                        )
+                    })
+                    .collect();
+                let memory: Vec<String> = accesses
+                    .iter()
+                    .map(|access| {
+                        format!(
+                            "{{\"adr\":{},\"size\":{},\"access\":\"{}\",\"old\":{},\"new\":{}}}",
+                            access.adr,
+                            access.size,
+                            access_name(access.access),
+                            access.old_value,
+                            access.new_value
+                        )
+                    })
+                    .collect();
+
+                writeln!(
+                    self.writer.borrow_mut(),
+                    "{{\"rip\":{},\"asm\":{},\"registers\":[{}],\"memory\":[{}]}}",
+                    rip,
+                    json_string(asm),
+                    registers.join(","),
+                    memory.join(",")
+                )
+            }
+        }
+    }
+}
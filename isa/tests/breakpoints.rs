@@ -0,0 +1,81 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::shared::{HaltReason, Register, Stop};
+
+const RISC_LOOP: &str = "main:
+  rega %1 1
+  jump @body
+body:
+  uadd %2 %2 %1
+  rega %18 18446744073709551615";
+
+const CISC_LOOP: &str = "main:
+  move %1 1
+  jump @body
+body:
+  uadd %2 %2 %1
+  move %18 18446744073709551615";
+
+#[test]
+fn risc_stops_before_breakpoints_and_resumes_past_them() {
+    let blocks = risc(RISC_LOOP);
+    let mut vm = risc_vm(&blocks);
+
+    assert_eq!(vm.add_breakpoint("body"), Some(2));
+    assert_eq!(vm.add_breakpoint(3), Some(3));
+    assert_eq!(vm.add_breakpoint("missing"), None);
+
+    assert_eq!(vm.run_until_breakpoint().unwrap(), Stop::Breakpoint(2));
+    assert_eq!(vm.registers().get(&Register::R2), 0);
+    assert_eq!(vm.run_until_breakpoint().unwrap(), Stop::Breakpoint(3));
+    assert_eq!(vm.registers().get(&Register::R2), 1);
+
+    assert!(vm.remove_breakpoint("body"));
+    assert!(!vm.remove_breakpoint("body"));
+    match vm.run_until_breakpoint().unwrap() {
+        Stop::Halted(status) => assert_eq!(status.reason, HaltReason::Returned),
+        stop => panic!("expected the program to halt, stopped with {:?}", stop),
+    }
+    assert!(vm.is_halted());
+}
+
+#[test]
+fn risc_steps_one_instruction_at_a_time() {
+    let blocks = risc(RISC_LOOP);
+    let mut vm = risc_vm(&blocks);
+
+    assert_eq!(vm.rip(), 0);
+    vm.step().unwrap();
+    assert_eq!(vm.rip(), 1);
+    assert_eq!(vm.registers().get(&Register::R1), 1);
+    vm.step().unwrap();
+    assert_eq!(vm.rip(), 2);
+    vm.step().unwrap();
+    vm.step().unwrap();
+    assert!(vm.is_halted());
+
+    // Stepping a halted VM does nothing
+    vm.step().unwrap();
+    assert_eq!(vm.perf().insts_retired, 4);
+}
+
+#[test]
+fn cisc_stops_before_breakpoints_and_resumes_past_them() {
+    let blocks = cisc(CISC_LOOP);
+    let mut vm = cisc_vm(&blocks);
+
+    assert_eq!(vm.add_breakpoint("body"), Some(2));
+    assert_eq!(vm.add_breakpoint("missing"), None);
+
+    assert_eq!(vm.run_until_breakpoint().unwrap(), Stop::Breakpoint(2));
+    assert_eq!(vm.registers().get(&Register::R2), 0);
+    vm.step().unwrap();
+    assert_eq!(vm.registers().get(&Register::R2), 1);
+    assert_eq!(vm.rip(), 3);
+
+    match vm.run_until_breakpoint().unwrap() {
+        Stop::Halted(status) => assert_eq!(status.reason, HaltReason::Returned),
+        stop => panic!("expected the program to halt, stopped with {:?}", stop),
+    }
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::{
+    cache::{Cache, CacheConfig, CacheStats, ReplacementPolicy, WritePolicy},
+    shared::Access,
+};
+
+// A single set of two lines, so the third line always evicts one of the others
+fn two_way(replacement: ReplacementPolicy) -> Cache {
+    Cache::new(CacheConfig {
+        size: 128,
+        line_size: 64,
+        associativity: 2,
+        replacement,
+        ..CacheConfig::default()
+    })
+}
+
+fn direct_mapped(write_policy: WritePolicy, write_allocate: bool) -> Cache {
+    Cache::new(CacheConfig {
+        size: 64,
+        line_size: 64,
+        associativity: 1,
+        write_policy,
+        write_allocate,
+        ..CacheConfig::default()
+    })
+}
+
+#[test]
+fn lru_evicts_the_least_recently_used_line() {
+    let mut cache = two_way(ReplacementPolicy::Lru);
+    assert!(!cache.access(0, 8, Access::Read));
+    assert!(!cache.access(64, 8, Access::Read));
+    assert!(cache.access(0, 8, Access::Read));
+    // Evicts 64, which was used longer ago than 0
+    assert!(!cache.access(128, 8, Access::Read));
+    assert!(cache.access(0, 8, Access::Read));
+    assert!(!cache.access(64, 8, Access::Read));
+
+    let stats = cache.stats();
+    assert_eq!(stats.read_hits, 2);
+    assert_eq!(stats.read_misses, 4);
+    assert_eq!(stats.evictions, 2);
+    assert_eq!(stats.hit_rate(), 2.0 / 6.0);
+}
+
+#[test]
+fn fifo_evicts_the_oldest_line() {
+    let mut cache = two_way(ReplacementPolicy::Fifo);
+    assert!(!cache.access(0, 8, Access::Read));
+    assert!(!cache.access(64, 8, Access::Read));
+    assert!(cache.access(0, 8, Access::Read));
+    // Evicts 0 even though it was just used
+    assert!(!cache.access(128, 8, Access::Read));
+    assert!(!cache.access(0, 8, Access::Read));
+}
+
+#[test]
+fn straddling_accesses_count_per_line() {
+    let mut cache = two_way(ReplacementPolicy::Lru);
+    assert!(!cache.access(60, 8, Access::Read));
+    assert_eq!(cache.stats().read_misses, 2);
+    assert!(cache.access(56, 16, Access::Read));
+    assert_eq!(cache.stats().read_hits, 2);
+}
+
+#[test]
+fn write_back_writes_dirty_lines_on_eviction() {
+    let mut cache = direct_mapped(WritePolicy::WriteBack, true);
+    assert!(!cache.access(0, 8, Access::Write));
+    assert!(cache.access(8, 8, Access::Write));
+    assert!(!cache.access(64, 8, Access::Read));
+
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            read_misses: 1,
+            write_hits: 1,
+            write_misses: 1,
+            evictions: 1,
+            write_backs: 1,
+            ..CacheStats::default()
+        }
+    );
+}
+
+#[test]
+fn write_through_without_allocate_goes_to_memory() {
+    let mut cache = direct_mapped(WritePolicy::WriteThrough, false);
+    assert!(!cache.access(0, 8, Access::Write));
+    // The write didn't bring the line in
+    assert!(!cache.access(0, 8, Access::Read));
+    assert!(cache.access(0, 8, Access::Write));
+
+    let stats = cache.stats();
+    assert_eq!(stats.memory_writes, 2);
+    assert_eq!(stats.write_backs, 0);
+}
+
+// One read miss, then a read and a write hitting the same line
+const VM_STATS: CacheStats = CacheStats {
+    read_hits: 1,
+    read_misses: 1,
+    write_hits: 1,
+    write_misses: 0,
+    evictions: 0,
+    write_backs: 0,
+    memory_writes: 0,
+};
+
+// Three memory accesses and one cache miss on top of five single cycle
+// instructions
+const VM_CYCLES: u64 = 5 + 3 * 2 + 20;
+
+#[test]
+fn risc_accesses_go_through_the_cache() {
+    let blocks = risc(
+        "main:
+          rega %1 64
+          load %2 %1
+          load %2 %1
+          store %1 %2
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    vm.set_cache(Some(Cache::new(CacheConfig::default())));
+    let status = vm.interpret();
+    assert_eq!(status.cache, Some(VM_STATS));
+    assert_eq!(status.perf.cycles, VM_CYCLES);
+}
+
+#[test]
+fn cisc_accesses_go_through_the_cache() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move %2 [%1]
+          move %2 [%1]
+          move [%1] %2
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    vm.set_cache(Some(Cache::new(CacheConfig::default())));
+    let status = vm.interpret();
+    assert_eq!(status.cache, Some(VM_STATS));
+    assert_eq!(status.perf.cycles, VM_CYCLES);
+}
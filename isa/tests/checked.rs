@@ -0,0 +1,113 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::shared::{Register, TrapReason};
+
+const MIN: u64 = i64::MIN as u64;
+const MAX: u64 = i64::MAX as u64;
+const NEG_ONE: u64 = -1i64 as u64;
+
+// Each checked instruction next to its wrapping counterpart, the operands and
+// what the wrapping one produces
+const OVERFLOWING: [(&str, &str, u64, u64, u64); 7] = [
+    ("saddchecked", "sadd", MAX, 1, MIN),
+    ("uaddchecked", "uadd", u64::MAX, 1, 0),
+    ("ssubchecked", "sub", MIN, 1, MAX),
+    ("usubchecked", "sub", 0, 1, u64::MAX),
+    ("smulchecked", "smul", MAX, 2, -2i64 as u64),
+    ("umulchecked", "umul", u64::MAX, 2, u64::MAX - 1),
+    ("sdivchecked", "sdiv", MIN, NEG_ONE, MIN),
+];
+
+const IN_RANGE: [(&str, u64, u64, u64); 7] = [
+    ("saddchecked", NEG_ONE, 1, 0),
+    ("uaddchecked", u64::MAX - 1, 1, u64::MAX),
+    ("ssubchecked", MIN, NEG_ONE, MIN + 1),
+    ("usubchecked", 5, 3, 2),
+    ("smulchecked", NEG_ONE, MAX, MIN + 1),
+    (
+        "umulchecked",
+        1 << 32,
+        (1 << 32) - 1,
+        u64::MAX - ((1 << 32) - 1),
+    ),
+    ("sdivchecked", MIN, 2, (i64::MIN / 2) as u64),
+];
+
+// Runs `inst` on the operands, leaving the result in R3. R3 starts at 7 so a
+// trap can be seen to leave it alone
+fn risc_op(inst: &str, lhs: u64, rhs: u64) -> Result<u64, TrapReason> {
+    let blocks = risc(&format!(
+        "main:
+          rega %1 {}
+          rega %2 {}
+          rega %3 7
+          {} %3 %1 %2
+          rega %18 18446744073709551615",
+        lhs, rhs, inst
+    ));
+    let mut vm = risc_vm(&blocks);
+    let status = vm.interpret();
+    match status.trap() {
+        Some(err) => {
+            assert_eq!(vm.registers().get(&Register::R3), 7);
+            Err(err.reason.clone())
+        }
+        None => Ok(vm.registers().get(&Register::R3)),
+    }
+}
+
+fn cisc_op(inst: &str, lhs: u64, rhs: u64) -> Result<u64, TrapReason> {
+    let blocks = cisc(&format!(
+        "main:
+          move %1 {}
+          move %3 7
+          {} %3 %1 {}
+          move %18 18446744073709551615",
+        lhs, inst, rhs
+    ));
+    let mut vm = cisc_vm(&blocks);
+    let status = vm.interpret();
+    match status.trap() {
+        Some(err) => {
+            assert_eq!(vm.registers().get(&Register::R3), 7);
+            Err(err.reason.clone())
+        }
+        None => Ok(vm.registers().get(&Register::R3)),
+    }
+}
+
+#[test]
+fn checked_insts_trap_where_wrapping_ones_wrap() {
+    for (checked, wrapping, lhs, rhs, wrapped) in OVERFLOWING.iter() {
+        for op in [risc_op, cisc_op].iter() {
+            assert_eq!(op(wrapping, *lhs, *rhs), Ok(*wrapped), "{}", wrapping);
+            assert_eq!(
+                op(checked, *lhs, *rhs),
+                Err(TrapReason::Overflow),
+                "{}",
+                checked
+            );
+        }
+    }
+}
+
+#[test]
+fn checked_insts_agree_with_wrapping_ones_in_range() {
+    for (checked, lhs, rhs, expected) in IN_RANGE.iter() {
+        assert_eq!(risc_op(checked, *lhs, *rhs), Ok(*expected), "{}", checked);
+        assert_eq!(cisc_op(checked, *lhs, *rhs), Ok(*expected), "{}", checked);
+    }
+}
+
+#[test]
+fn checked_division_by_zero_is_not_an_overflow() {
+    assert_eq!(
+        risc_op("sdivchecked", 1, 0),
+        Err(TrapReason::DivisionByZero)
+    );
+    assert_eq!(
+        cisc_op("sdivchecked", 1, 0),
+        Err(TrapReason::DivisionByZero)
+    );
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::{
+    clock::{Clock, ClockMode},
+    shared::Register,
+};
+use std::time::Duration;
+
+const SLEEP_NS: u64 = 5_000_000;
+
+#[test]
+fn virtual_clocks_only_move_when_told_to() {
+    let mut clock = Clock::default();
+    assert_eq!(clock.mode(), ClockMode::Virtual);
+    assert_eq!(clock.now_ns(), 0);
+    clock.sleep(Duration::from_millis(5));
+    assert_eq!(clock.now_ns(), SLEEP_NS);
+}
+
+#[test]
+fn wall_clocks_sleep_for_real() {
+    let mut clock = Clock::wall_time();
+    assert_eq!(clock.mode(), ClockMode::Wall);
+    clock.sleep(Duration::from_millis(5));
+    assert!(clock.now_ns() >= SLEEP_NS);
+}
+
+// Reads the time, sleeps for 5 ms then reads the time again, into R10 and R1
+#[test]
+fn risc_time_follows_cycles_and_sleeps() {
+    let blocks = risc(
+        "main:
+          rega %9 11
+          syscall %9
+          copy %10 %1
+          rega %1 5
+          rega %9 5
+          syscall %9
+          rega %9 11
+          syscall %9
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    vm.set_clock(Clock::virtual_time(10));
+    let status = vm.interpret();
+
+    // Single cycle instructions and 50 cycle syscalls, at 10 ns a cycle
+    assert_eq!(vm.registers().get(&Register::R10), 10);
+    assert_eq!(vm.registers().get(&Register::R1), SLEEP_NS + 105 * 10);
+    assert_eq!(vm.clock().now_ns(), SLEEP_NS + status.perf.cycles * 10);
+}
+
+#[test]
+fn cisc_time_follows_cycles_and_sleeps() {
+    let blocks = cisc(
+        "main:
+          syscall 11
+          move %10 %1
+          move %1 5
+          syscall 5
+          syscall 11
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    vm.set_clock(Clock::virtual_time(10));
+    let status = vm.interpret();
+
+    assert_eq!(vm.registers().get(&Register::R10), 0);
+    assert_eq!(vm.registers().get(&Register::R1), SLEEP_NS + 102 * 10);
+    assert_eq!(vm.clock().now_ns(), SLEEP_NS + status.perf.cycles * 10);
+}
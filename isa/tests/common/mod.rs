@@ -0,0 +1,27 @@
+// Not every test uses every helper
+#![allow(dead_code)]
+
+use isa::{cisc, memory::Memory, risc};
+use std::io;
+
+pub type RiscVm<'a> = risc::vm::VM<'a, io::Empty, Vec<u8>>;
+pub type CiscVm<'a> = cisc::vm::VM<'a, io::Empty, Vec<u8>>;
+
+// Both VMs halt once `Rip` is set to this
+pub const HALT: u64 = u64::MAX;
+
+pub fn risc(source: &str) -> Vec<risc::inst::Block> {
+    risc::asm::parse(source).unwrap()
+}
+
+pub fn cisc(source: &str) -> Vec<cisc::inst::Block> {
+    cisc::asm::parse(source).unwrap()
+}
+
+pub fn risc_vm(blocks: &[risc::inst::Block]) -> RiscVm<'_> {
+    risc::vm::VM::new(blocks, io::empty(), Vec::new(), Memory::flat(1024))
+}
+
+pub fn cisc_vm(blocks: &[cisc::inst::Block]) -> CiscVm<'_> {
+    cisc::vm::VM::new(blocks, io::empty(), Vec::new(), Memory::flat(1024))
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::shared::Register;
+
+fn f64_bits(value: f64) -> u64 {
+    value.to_bits()
+}
+
+fn f32_bits(value: f32) -> u64 {
+    value.to_bits() as u64
+}
+
+// Each instruction applied to a value, and what it should produce. Float to int
+// conversions saturate and send NaN to 0
+fn cases() -> Vec<(&'static str, u64, u64)> {
+    vec![
+        ("stof", -3i64 as u64, f64_bits(-3.0)),
+        ("utof", u64::MAX, f64_bits(18446744073709551615.0)),
+        ("ftos", f64_bits(-2.7), -2i64 as u64),
+        ("ftos", f64_bits(1e300), i64::MAX as u64),
+        ("ftos", f64_bits(f64::NAN), 0),
+        ("ftou", f64_bits(2.7), 2),
+        ("ftou", f64_bits(-1.0), 0),
+        ("ftosround", f64_bits(-2.5), -3i64 as u64),
+        ("ftosround", f64_bits(-2.4), -2i64 as u64),
+        ("ftouround", f64_bits(2.5), 3),
+        ("f32tof64", f32_bits(0.1), f64_bits(0.1f32 as f64)),
+        ("f64tof32", f64_bits(0.1), f32_bits(0.1)),
+        ("f64tof32", f64_bits(1e300), f32_bits(f32::INFINITY)),
+    ]
+}
+
+#[test]
+fn risc_conversions() {
+    for (inst, value, expected) in cases() {
+        let blocks = risc(&format!(
+            "main:\n  rega %1 {}\n  {} %2 %1\n  rega %18 18446744073709551615",
+            value, inst
+        ));
+        let mut vm = risc_vm(&blocks);
+        vm.interpret();
+        assert_eq!(
+            vm.registers().get(&Register::R2),
+            expected,
+            "{} {:#x}",
+            inst,
+            value
+        );
+    }
+}
+
+#[test]
+fn cisc_conversions() {
+    for (inst, value, expected) in cases() {
+        let blocks = cisc(&format!(
+            "main:\n  move %1 {}\n  {} %2 %1\n  move %18 18446744073709551615",
+            value, inst
+        ));
+        let mut vm = cisc_vm(&blocks);
+        vm.interpret();
+        assert_eq!(
+            vm.registers().get(&Register::R2),
+            expected,
+            "{} {:#x}",
+            inst,
+            value
+        );
+    }
+}
+
+// Conversions can also read and write memory directly
+#[test]
+fn cisc_conversions_take_memory_operands() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move [%1] 18446744073709551614
+          stof [%1] [%1]
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    assert!(vm.interpret().trap().is_none());
+    assert_eq!(vm.memory().read_uint(64, 8), Ok(f64_bits(-2.0)));
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::{
+    cache::{Cache, CacheConfig},
+    device::{Console, Framebuffer, Timer},
+    shared::{Register, TrapReason},
+};
+use std::{cell::RefCell, rc::Rc};
+
+#[test]
+fn risc_stores_reach_the_console() {
+    let blocks = risc(
+        "main:
+          rega %1 512
+          rega %2 104
+          store8 %1 %2
+          rega %2 105
+          store8 %1 %2
+          rega %18 18446744073709551615",
+    );
+    let console = Rc::new(RefCell::new(Console::new(Vec::new())));
+    let mut vm = risc_vm(&blocks);
+    vm.map_device(512..520, console.clone());
+    vm.set_cache(Some(Cache::new(CacheConfig::default())));
+    let status = vm.interpret();
+
+    assert_eq!(console.borrow().writer(), b"hi");
+    // Nothing went to memory or through the cache
+    assert_eq!(vm.memory().read_uint(512, 8), Ok(0));
+    assert_eq!(status.cache.unwrap().accesses(), 0);
+}
+
+#[test]
+fn cisc_moves_reach_the_framebuffer() {
+    let blocks = cisc(
+        "main:
+          move %1 514
+          move8 [%1] 255
+          move8 %2 [%1]
+          move %18 18446744073709551615",
+    );
+    let framebuffer = Rc::new(RefCell::new(Framebuffer::new(4, 2)));
+    let mut vm = cisc_vm(&blocks);
+    vm.map_device(512..520, framebuffer.clone());
+    vm.interpret();
+
+    assert_eq!(framebuffer.borrow().pixels(), [0, 0, 255, 0, 0, 0, 0, 0]);
+    assert_eq!(vm.registers().get(&Register::R2), 255);
+}
+
+#[test]
+fn timer_counts_executed_insts() {
+    let blocks = risc(
+        "main:
+          rega %1 512
+          rega %2 0
+          load %3 %1
+          store %1 %2
+          load %4 %1
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    vm.map_device(512..520, Rc::new(RefCell::new(Timer::new())));
+    vm.interpret();
+
+    assert_eq!(vm.registers().get(&Register::R3), 2);
+    assert_eq!(vm.registers().get(&Register::R4), 1);
+}
+
+// Accesses sticking out of either end of the device at 512..520
+const STRADDLING: [u64; 2] = [508, 516];
+
+#[test]
+fn risc_accesses_straddling_a_device_trap() {
+    for adr in STRADDLING.iter() {
+        let blocks = risc(&format!(
+            "main:
+              rega %1 {}
+              store %1 %1",
+            adr
+        ));
+        let console = Rc::new(RefCell::new(Console::new(Vec::new())));
+        let mut vm = risc_vm(&blocks);
+        vm.map_device(512..520, console.clone());
+        let status = vm.interpret();
+
+        assert_eq!(
+            status.trap().unwrap().reason,
+            TrapReason::MemoryOutOfBounds(*adr)
+        );
+        assert!(console.borrow().writer().is_empty());
+        assert_eq!(vm.memory().to_vec(504..528), Ok(vec![0; 24]));
+    }
+}
+
+#[test]
+fn cisc_accesses_straddling_a_device_trap() {
+    for adr in STRADDLING.iter() {
+        let blocks = cisc(&format!(
+            "main:
+              move %1 {}
+              move [%1] %1",
+            adr
+        ));
+        let console = Rc::new(RefCell::new(Console::new(Vec::new())));
+        let mut vm = cisc_vm(&blocks);
+        vm.map_device(512..520, console.clone());
+        let status = vm.interpret();
+
+        assert_eq!(
+            status.trap().unwrap().reason,
+            TrapReason::MemoryOutOfBounds(*adr)
+        );
+        assert!(console.borrow().writer().is_empty());
+        assert_eq!(vm.memory().to_vec(504..528), Ok(vec![0; 24]));
+    }
+}
+
+#[test]
+#[should_panic(expected = "overlaps already mapped range")]
+fn overlapping_devices_are_rejected() {
+    let blocks = risc("main:\n  rega %18 18446744073709551615");
+    let mut vm = risc_vm(&blocks);
+    vm.map_device(512..520, Rc::new(RefCell::new(Timer::new())));
+    vm.map_device(516..524, Rc::new(RefCell::new(Timer::new())));
+}
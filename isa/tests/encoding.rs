@@ -0,0 +1,262 @@
+use isa::{
+    cisc,
+    encoding::{DecodeError, EncodeError},
+    risc,
+    shared::{Imm, Label, Register},
+};
+
+fn risc_block(label: &str, insts: Vec<risc::inst::Inst>) -> risc::inst::Block {
+    risc::inst::Block {
+        label: label.into(),
+        insts,
+    }
+}
+
+fn cisc_block(label: &str, insts: Vec<cisc::inst::Inst>) -> cisc::inst::Block {
+    cisc::inst::Block {
+        label: label.into(),
+        insts,
+    }
+}
+
+// Decoded labels are named after the instruction index they point at, so the
+// blocks here follow the same naming to compare as assembly
+#[test]
+fn risc_round_trips() {
+    use risc::inst::Inst;
+
+    let blocks = vec![
+        risc_block(
+            "L0",
+            vec![
+                Inst::Rega(Register::R1, Imm::Int(0)),
+                // The largest and smallest values the short form holds, and
+                // one past each of them
+                Inst::Rega(Register::R1, Imm::Int(262143)),
+                Inst::Rega(Register::R1, Imm::Int(262144)),
+                Inst::Rega(Register::R2, Imm::Int(-262144i64 as u64)),
+                Inst::Rega(Register::R2, Imm::Int(-262145i64 as u64)),
+                Inst::Rega(Register::R3, Imm::Int(u64::MAX)),
+                Inst::Rega(Register::R3, Imm::Int(i64::MIN as u64)),
+                Inst::SAdd(Register::R15, Register::R14, Register::R13),
+                Inst::Load32S(Register::R4, Register::Rsp),
+                Inst::Branch(Register::R1, Label::new("L0"), Label::new("L11")),
+                Inst::Jump(Label::new("L11")),
+            ],
+        ),
+        risc_block(
+            "L11",
+            vec![
+                Inst::CJump(Register::Rip, Label::new("L0")),
+                Inst::SysCall(Register::R0),
+            ],
+        ),
+    ];
+
+    let bytes = risc::encoding::encode(&blocks).unwrap();
+    // Three immediates need the wide form and the branch takes two words
+    assert_eq!(bytes.len(), (13 + 3 * 2 + 1) * 4);
+
+    let decoded = risc::encoding::decode(&bytes).unwrap();
+    let original = blocks.iter().flat_map(|block| &block.insts);
+    assert_eq!(decoded.len(), 13);
+    for (decoded, original) in decoded.iter().zip(original) {
+        assert_eq!(decoded.as_asm(), original.as_asm());
+    }
+}
+
+#[test]
+fn risc_float_immediates_decode_as_their_bits() {
+    use risc::inst::Inst;
+
+    let blocks = vec![risc_block(
+        "main",
+        vec![Inst::Rega(Register::R1, Imm::Float(-2.5))],
+    )];
+    let bytes = risc::encoding::encode(&blocks).unwrap();
+    let decoded = risc::encoding::decode(&bytes).unwrap();
+    assert_eq!(
+        decoded[0].as_asm(),
+        Inst::Rega(Register::R1, Imm::Int((-2.5f64).to_bits())).as_asm()
+    );
+}
+
+#[test]
+fn risc_encode_rejects_bad_labels() {
+    use risc::inst::Inst;
+
+    let blocks = vec![risc_block("main", vec![Inst::Jump(Label::new("nowhere"))])];
+    assert_eq!(
+        risc::encoding::encode(&blocks).unwrap_err(),
+        EncodeError::UndefinedLabel("nowhere".into())
+    );
+
+    // `cjump` only has 19 bits for its target
+    let blocks = vec![
+        risc_block(
+            "main",
+            vec![Inst::CJump(Register::R1, Label::new("far")); 1 << 19],
+        ),
+        risc_block("far", vec![Inst::SysCall(Register::R0)]),
+    ];
+    assert_eq!(
+        risc::encoding::encode(&blocks).unwrap_err(),
+        EncodeError::TargetOutOfRange("far".into(), 19)
+    );
+}
+
+#[test]
+fn risc_decode_rejects_malformed_bytes() {
+    use risc::inst::Inst;
+
+    assert_eq!(
+        risc::encoding::decode(&[0x03, 0x00, 0x00]).unwrap_err(),
+        DecodeError::UnexpectedEnd(0)
+    );
+
+    let blocks = vec![risc_block(
+        "main",
+        vec![
+            Inst::SysCall(Register::R0),
+            Inst::Rega(Register::R1, Imm::Int(u64::MAX / 2)),
+        ],
+    )];
+    let bytes = risc::encoding::encode(&blocks).unwrap();
+    assert_eq!(
+        risc::encoding::decode(&bytes[..bytes.len() - 4]).unwrap_err(),
+        DecodeError::UnexpectedEnd(12)
+    );
+
+    assert_eq!(
+        risc::encoding::decode(&[0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00]).unwrap_err(),
+        DecodeError::UnknownOpcode(4, 0xff)
+    );
+
+    // `copy` with a destination register ID of 31
+    assert_eq!(
+        risc::encoding::decode(&[0x03, 0x1f, 0x00, 0x00]).unwrap_err(),
+        DecodeError::InvalidRegister(0, 31)
+    );
+}
+
+#[test]
+fn cisc_round_trips() {
+    use cisc::inst::{Inst, Operand, Target};
+
+    let blocks = vec![
+        cisc_block(
+            "L0",
+            vec![
+                Inst::Move(Operand::Adr(Register::Rsp), Operand::Data(Register::R15)),
+                Inst::Move32S(Operand::Data(Register::R1), Operand::Adr(Register::Rfp)),
+                Inst::SAdd(
+                    Operand::Data(Register::R2),
+                    Operand::Data(Register::R2),
+                    Operand::Imm(Imm::Int(-5i64 as u64)),
+                ),
+                Inst::Branch(
+                    Operand::Data(Register::R1),
+                    Target::Label(Label::new("L0")),
+                    Target::Label(Label::new("L6")),
+                ),
+                Inst::Jump(Target::Pointer(Register::R0)),
+                Inst::CJump(Operand::Imm(Imm::Int(1)), Target::Label(Label::new("L6"))),
+            ],
+        ),
+        cisc_block("L6", vec![Inst::SysCall(Operand::Imm(Imm::Int(0)))]),
+    ];
+
+    let bytes = cisc::encoding::encode(&blocks).unwrap();
+    let decoded = cisc::encoding::decode(&bytes).unwrap();
+    let original = blocks.iter().flat_map(|block| &block.insts);
+    assert_eq!(decoded.len(), 7);
+    for (decoded, original) in decoded.iter().zip(original) {
+        assert_eq!(decoded.as_asm(), original.as_asm());
+    }
+}
+
+#[test]
+fn cisc_immediates_take_the_smallest_width() {
+    use cisc::inst::{Inst, Operand};
+
+    // Each width's limits and the values just past them
+    let widths = [
+        (0, 1),
+        (i8::MAX as i64, 1),
+        (i8::MAX as i64 + 1, 2),
+        (i8::MIN as i64, 1),
+        (i8::MIN as i64 - 1, 2),
+        (i16::MAX as i64, 2),
+        (i16::MAX as i64 + 1, 4),
+        (i16::MIN as i64, 2),
+        (i16::MIN as i64 - 1, 4),
+        (i32::MAX as i64, 4),
+        (i32::MAX as i64 + 1, 8),
+        (i32::MIN as i64, 4),
+        (i32::MIN as i64 - 1, 8),
+        (i64::MAX, 8),
+        (i64::MIN, 8),
+    ];
+
+    for (value, width) in widths.iter() {
+        let inst = Inst::Move(
+            Operand::Data(Register::R1),
+            Operand::Imm(Imm::Int(*value as u64)),
+        );
+        let bytes = cisc::encoding::encode(&[cisc_block("main", vec![inst.clone()])]).unwrap();
+        // Opcode and two mode bytes
+        assert_eq!(bytes.len(), 3 + width, "{}", value);
+
+        let decoded = cisc::encoding::decode(&bytes).unwrap();
+        assert_eq!(decoded[0].as_asm(), inst.as_asm());
+    }
+}
+
+#[test]
+fn cisc_encode_rejects_undefined_labels() {
+    use cisc::inst::{Inst, Target};
+
+    let blocks = vec![cisc_block(
+        "main",
+        vec![Inst::Jump(Target::Label(Label::new("nowhere")))],
+    )];
+    assert_eq!(
+        cisc::encoding::encode(&blocks).unwrap_err(),
+        EncodeError::UndefinedLabel("nowhere".into())
+    );
+}
+
+#[test]
+fn cisc_decode_rejects_malformed_bytes() {
+    use cisc::inst::{Inst, Operand};
+
+    let blocks = vec![cisc_block(
+        "main",
+        vec![Inst::Move(
+            Operand::Data(Register::R1),
+            Operand::Imm(Imm::Int(1 << 40)),
+        )],
+    )];
+    let bytes = cisc::encoding::encode(&blocks).unwrap();
+    assert_eq!(
+        cisc::encoding::decode(&bytes[..bytes.len() - 1]).unwrap_err(),
+        DecodeError::UnexpectedEnd(3)
+    );
+
+    assert_eq!(
+        cisc::encoding::decode(&[0xff]).unwrap_err(),
+        DecodeError::UnknownOpcode(0, 0xff)
+    );
+
+    // `move` into register 31
+    assert_eq!(
+        cisc::encoding::decode(&[0x01, 0b000_11111, 0b000_00001]).unwrap_err(),
+        DecodeError::InvalidRegister(1, 31)
+    );
+
+    // Labels are only valid as jump targets
+    assert_eq!(
+        cisc::encoding::decode(&[0x01, 0b110_00000, 0, 0, 0, 0]).unwrap_err(),
+        DecodeError::InvalidMode(1, 0b110)
+    );
+}
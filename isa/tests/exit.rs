@@ -0,0 +1,60 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::shared::{HaltReason, Register, TrapReason};
+
+#[test]
+fn risc_exit_halts_with_its_code() {
+    let blocks = risc(
+        "main:
+          rega %1 18446744073709551613
+          rega %9 12
+          syscall %9
+          rega %2 1
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    let status = vm.interpret();
+
+    assert_eq!(status.reason, HaltReason::Exited);
+    assert_eq!(status.code, -3);
+    assert!(vm.is_halted());
+    // Nothing after the syscall ran
+    assert_eq!(vm.registers().get(&Register::R2), 0);
+    assert_eq!(status.insts(), 3);
+}
+
+#[test]
+fn cisc_exit_halts_with_its_code() {
+    let blocks = cisc(
+        "main:
+          move %1 7
+          syscall 12
+          move %2 1
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    let status = vm.interpret();
+
+    assert_eq!(status.reason, HaltReason::Exited);
+    assert_eq!(status.code, 7);
+    assert_eq!(vm.registers().get(&Register::R2), 0);
+}
+
+#[test]
+fn returning_and_trapping_have_fixed_codes() {
+    let blocks = risc("main:\n  rega %1 5\n  rega %18 18446744073709551615");
+    let status = risc_vm(&blocks).interpret();
+    assert_eq!(status.reason, HaltReason::Returned);
+    assert_eq!(status.code, 0);
+
+    let blocks = cisc("main:\n  move %1 5\n  move %18 18446744073709551615");
+    let status = cisc_vm(&blocks).interpret();
+    assert_eq!(status.reason, HaltReason::Returned);
+    assert_eq!(status.code, 0);
+
+    let blocks = cisc("main:\n  move %1 0\n  udiv %1 %1 %1");
+    let status = cisc_vm(&blocks).interpret();
+    assert_eq!(status.code, 1);
+    assert_eq!(status.trap().unwrap().reason, TrapReason::DivisionByZero);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::shared::Register;
+
+fn bits(value: f32) -> u64 {
+    value.to_bits() as u64
+}
+
+const LHS: f32 = 16777216.0;
+const RHS: f32 = 3.0;
+
+// Results are rounded to f32, which can't represent 16777219
+fn arithmetic() -> Vec<(&'static str, u64)> {
+    vec![
+        ("fadd32", bits(LHS + RHS)),
+        ("fsub32", bits(LHS - RHS)),
+        ("fmul32", bits(LHS * RHS)),
+        ("fdiv32", bits(LHS / RHS)),
+        ("frem32", bits(LHS % RHS)),
+    ]
+}
+
+fn comparisons() -> Vec<(&'static str, bool)> {
+    vec![("feq32", false), ("flt32", false), ("fgt32", true)]
+}
+
+// Applies `inst` to `LHS` and `RHS`, returning the result
+fn risc_op(inst: &str) -> u64 {
+    let blocks = risc(&format!(
+        "main:\n  rega %1 {}\n  rega %2 {}\n  {} %3 %1 %2\n  rega %18 18446744073709551615",
+        bits(LHS),
+        bits(RHS),
+        inst
+    ));
+    let mut vm = risc_vm(&blocks);
+    vm.interpret();
+    vm.registers().get(&Register::R3)
+}
+
+fn cisc_op(inst: &str) -> u64 {
+    let blocks = cisc(&format!(
+        "main:\n  move %1 {}\n  {} %3 %1 {}\n  move %18 18446744073709551615",
+        bits(LHS),
+        inst,
+        bits(RHS)
+    ));
+    let mut vm = cisc_vm(&blocks);
+    vm.interpret();
+    vm.registers().get(&Register::R3)
+}
+
+#[test]
+fn f32_ops_round_to_f32() {
+    assert_eq!(bits(LHS + RHS), bits(16777220.0));
+
+    for (inst, expected) in arithmetic() {
+        assert_eq!(risc_op(inst), expected, "{}", inst);
+        assert_eq!(cisc_op(inst), expected, "{}", inst);
+    }
+    for (inst, expected) in comparisons() {
+        assert_eq!(risc_op(inst) != 0, expected, "{}", inst);
+        assert_eq!(cisc_op(inst) != 0, expected, "{}", inst);
+    }
+}
+
+// Only the low 32 bits of the operands are read
+#[test]
+fn f32_ops_ignore_the_upper_half() {
+    let blocks = risc(&format!(
+        "main:\n  rega %1 {}\n  rega %2 {}\n  fadd32 %3 %1 %2\n  rega %18 18446744073709551615",
+        bits(1.5) | 0xdead_beef_0000_0000,
+        bits(RHS)
+    ));
+    let mut vm = risc_vm(&blocks);
+    vm.interpret();
+    assert_eq!(vm.registers().get(&Register::R3), bits(4.5));
+}
@@ -0,0 +1,251 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::{
+    cisc,
+    fs::{OpenMode, VirtualFs, MAX_FILE_SIZE},
+    memory::Memory,
+    shared::{Access, Register, TrapReason, WatchKind},
+    syscall::{FS_ERROR, MAX_TRANSFER},
+};
+use std::io::{self, SeekFrom};
+
+#[test]
+fn files_are_read_and_written_through_descriptors() {
+    let mut fs = VirtualFs::new();
+    assert_eq!(fs.open("missing", OpenMode::Read), None);
+
+    let fd = fs.open("log", OpenMode::Write).unwrap();
+    assert_eq!(fd, 3);
+    assert_eq!(fs.write(fd, b"abc"), Some(3));
+    // Writing past the end fills the gap with zeros
+    assert_eq!(fs.seek(fd, SeekFrom::End(2)), Some(5));
+    assert_eq!(fs.write(fd, b"d"), Some(1));
+    assert_eq!(fs.get("log"), Some(&b"abc\0\0d"[..]));
+    assert_eq!(fs.read(fd, &mut [0; 4]), None);
+    assert_eq!(fs.close(fd), Some(()));
+    assert_eq!(fs.close(fd), None);
+
+    let fd = fs.open("log", OpenMode::Append).unwrap();
+    assert_eq!(fs.seek(fd, SeekFrom::Start(0)), Some(0));
+    fs.write(fd, b"e").unwrap();
+    assert_eq!(fs.get("log"), Some(&b"abc\0\0de"[..]));
+
+    let fd = fs.open("log", OpenMode::Read).unwrap();
+    let mut buf = [0; 4];
+    assert_eq!(fs.seek(fd, SeekFrom::Current(-1)), None);
+    assert_eq!(fs.seek(fd, SeekFrom::End(-3)), Some(4));
+    assert_eq!(fs.read(fd, &mut buf), Some(3));
+    assert_eq!(&buf[..3], b"\0de");
+    assert_eq!(fs.read(fd, &mut buf), Some(0));
+    assert_eq!(fs.write(fd, b"f"), None);
+}
+
+#[test]
+fn files_cannot_grow_past_the_maximum_size() {
+    let mut fs = VirtualFs::new();
+    let fd = fs.open("big", OpenMode::Write).unwrap();
+
+    assert_eq!(fs.seek(fd, SeekFrom::Start(MAX_FILE_SIZE + 1)), None);
+    assert_eq!(fs.seek(fd, SeekFrom::Start(u64::MAX)), None);
+    assert_eq!(
+        fs.seek(fd, SeekFrom::Start(MAX_FILE_SIZE)),
+        Some(MAX_FILE_SIZE)
+    );
+    assert_eq!(fs.seek(fd, SeekFrom::Current(1)), None);
+    assert_eq!(fs.write(fd, b"x"), None);
+    assert_eq!(fs.get("big"), Some(&b""[..]));
+
+    // Filling the file right up to the limit is fine
+    assert_eq!(fs.seek(fd, SeekFrom::Current(-1)), Some(MAX_FILE_SIZE - 1));
+    assert_eq!(fs.write(fd, b"x"), Some(1));
+    assert_eq!(fs.get("big").unwrap().len() as u64, MAX_FILE_SIZE);
+}
+
+// Copies `in` to `out`, skipping its first two bytes. The paths are at 64 and
+// 72, the copy goes through 128
+#[test]
+fn risc_copies_files() {
+    let blocks = risc(
+        "main:
+          rega %1 64
+          rega %2 2
+          rega %3 0
+          rega %9 13
+          syscall %9
+          copy %10 %1
+          rega %2 2
+          rega %3 0
+          rega %9 16
+          syscall %9
+          copy %1 %10
+          rega %2 128
+          rega %3 16
+          rega %9 14
+          syscall %9
+          copy %12 %1
+          rega %1 72
+          rega %2 3
+          rega %3 1
+          rega %9 13
+          syscall %9
+          rega %2 128
+          copy %3 %12
+          rega %9 15
+          syscall %9
+          copy %13 %1
+          copy %1 %10
+          rega %9 17
+          syscall %9
+          copy %1 %10
+          rega %9 17
+          syscall %9
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    vm.memory_mut().write(64, b"in").unwrap();
+    vm.memory_mut().write(72, b"out").unwrap();
+    vm.fs_mut().insert("in", "..copied");
+    assert!(vm.interpret().trap().is_none());
+
+    assert_eq!(vm.fs().get("out"), Some(&b"copied"[..]));
+    assert_eq!(vm.registers().get(&Register::R12), 6);
+    assert_eq!(vm.registers().get(&Register::R13), 6);
+    // Closing twice fails the second time
+    assert_eq!(vm.registers().get(&Register::R1), FS_ERROR);
+}
+
+#[test]
+fn risc_read_stores_like_store_instructions() {
+    let blocks = risc(
+        "main:
+          rega %1 64
+          rega %2 2
+          rega %3 0
+          rega %9 13
+          syscall %9
+          rega %2 128
+          rega %3 16
+          rega %9 14
+          syscall %9
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    vm.memory_mut().write(64, b"in").unwrap();
+    vm.fs_mut().insert("in", "copied");
+    vm.record_history(true);
+    vm.add_watchpoint(132..134, WatchKind::Write);
+    for _ in 0..8 {
+        assert_eq!(vm.step(), Ok(None));
+    }
+
+    // 6 bytes take a 4 and a 2 byte store
+    let hit = vm.step().unwrap().unwrap();
+    assert_eq!((hit.adr, hit.size, hit.access), (132, 2, Access::Write));
+    assert_eq!(vm.perf().stores, 2);
+    assert_eq!(vm.memory().to_vec(128..134), Ok(b"copied".to_vec()));
+
+    assert!(vm.step_back());
+    assert_eq!(vm.rip(), 8);
+    assert_eq!(vm.memory().to_vec(128..134), Ok(vec![0; 6]));
+}
+
+#[test]
+fn cisc_copies_files() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move %2 2
+          move %3 0
+          syscall 13
+          move %10 %1
+          move %2 2
+          move %3 0
+          syscall 16
+          move %1 %10
+          move %2 128
+          move %3 16
+          syscall 14
+          move %12 %1
+          move %1 72
+          move %2 3
+          move %3 1
+          syscall 13
+          move %2 128
+          move %3 %12
+          syscall 15
+          move %13 %1
+          move %1 %10
+          syscall 17
+          move %1 %10
+          syscall 17
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    vm.memory_mut().write(64, b"in").unwrap();
+    vm.memory_mut().write(72, b"out").unwrap();
+    vm.fs_mut().insert("in", "..copied");
+    assert!(vm.interpret().trap().is_none());
+
+    assert_eq!(vm.fs().get("out"), Some(&b"copied"[..]));
+    assert_eq!(vm.registers().get(&Register::R12), 6);
+    assert_eq!(vm.registers().get(&Register::R13), 6);
+    assert_eq!(vm.registers().get(&Register::R1), FS_ERROR);
+}
+
+// Writes `%3` bytes from 0 to a new file named by the byte at 0, then seeks
+// to `%4` from the start
+fn cisc_write_and_seek(len: u64, pos: u64) -> (Result<(), TrapReason>, [u64; 2]) {
+    let blocks = cisc(&format!(
+        "main:
+          move %1 0
+          move %2 1
+          move %3 1
+          syscall 13
+          move %10 %1
+          move %2 0
+          move %3 {}
+          syscall 15
+          move %11 %1
+          move %1 %10
+          move %2 {}
+          move %3 0
+          syscall 16
+          move %18 18446744073709551615",
+        len, pos
+    ));
+    // Sparse memory can be far larger than what a syscall is allowed to copy
+    let mut vm = cisc::vm::VM::new(&blocks, io::empty(), Vec::new(), Memory::sparse(1 << 40));
+    vm.memory_mut().write(0, b"f").unwrap();
+    let status = vm.interpret();
+    let result = status.trap().map_or(Ok(()), |err| Err(err.reason.clone()));
+    (
+        result,
+        [
+            vm.registers().get(&Register::R11),
+            vm.registers().get(&Register::R1),
+        ],
+    )
+}
+
+#[test]
+fn oversized_transfers_and_positions_fail() {
+    assert_eq!(
+        cisc_write_and_seek(MAX_TRANSFER, MAX_FILE_SIZE),
+        (Ok(()), [MAX_TRANSFER, MAX_FILE_SIZE])
+    );
+    assert_eq!(
+        cisc_write_and_seek(MAX_TRANSFER + 1, MAX_FILE_SIZE + 1),
+        (Ok(()), [FS_ERROR, FS_ERROR])
+    );
+    assert_eq!(
+        cisc_write_and_seek(1 << 39, u64::MAX),
+        (Ok(()), [FS_ERROR, FS_ERROR])
+    );
+
+    // Ranges outside of memory still trap
+    assert_eq!(
+        cisc_write_and_seek((1 << 40) + 1, 0).0,
+        Err(TrapReason::MemoryOutOfBounds(0))
+    );
+}
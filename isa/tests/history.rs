@@ -0,0 +1,85 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::shared::Register;
+
+#[test]
+fn risc_steps_back_over_registers_and_memory() {
+    let blocks = risc(
+        "main:
+          rega %1 64
+          rega %2 42
+          store %1 %2
+          rega %2 7
+          store %1 %2
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    vm.record_history(true);
+    vm.interpret();
+    assert_eq!(vm.memory().read_uint(64, 8).unwrap(), 7);
+
+    // Undoes the halt, then the second store
+    assert!(vm.step_back());
+    assert_eq!(vm.rip(), 5);
+    assert!(vm.step_back());
+    assert_eq!(vm.rip(), 4);
+    assert_eq!(vm.memory().read_uint(64, 8).unwrap(), 42);
+    assert!(vm.step_back());
+    assert_eq!(vm.registers().get(&Register::R2), 42);
+
+    vm.add_breakpoint(2);
+    assert_eq!(vm.run_back_to_breakpoint(), Some(2));
+    assert_eq!(vm.memory().read_uint(64, 8).unwrap(), 0);
+    assert_eq!(vm.registers().get(&Register::R1), 64);
+
+    assert_eq!(vm.run_back_to_breakpoint(), None);
+    assert_eq!(vm.rip(), 0);
+    assert_eq!(vm.registers().get(&Register::R1), 0);
+    assert_eq!(vm.perf().insts_retired, 0);
+    assert!(!vm.step_back());
+
+    // Running forward again replays the same program
+    vm.interpret();
+    assert_eq!(vm.memory().read_uint(64, 8).unwrap(), 7);
+}
+
+#[test]
+fn risc_history_is_off_by_default() {
+    let blocks = risc("main:\n  rega %1 1\n  rega %18 18446744073709551615");
+    let mut vm = risc_vm(&blocks);
+    vm.step().unwrap();
+    assert!(!vm.step_back());
+
+    vm.record_history(true);
+    vm.step().unwrap();
+    vm.record_history(false);
+    assert!(!vm.step_back());
+}
+
+#[test]
+fn cisc_steps_back_over_registers_and_memory() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move [%1] 42
+          move [%1] 7
+          sadd %2 [%1] 1
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    vm.record_history(true);
+    vm.interpret();
+    assert_eq!(vm.registers().get(&Register::R2), 8);
+
+    vm.add_breakpoint(2);
+    assert_eq!(vm.run_back_to_breakpoint(), Some(2));
+    assert_eq!(vm.memory().read_uint(64, 8).unwrap(), 42);
+    assert_eq!(vm.registers().get(&Register::R2), 0);
+
+    assert!(vm.step_back());
+    assert_eq!(vm.memory().read_uint(64, 8).unwrap(), 0);
+    assert!(vm.step_back());
+    assert_eq!(vm.registers().get(&Register::R1), 0);
+    assert!(!vm.step_back());
+}
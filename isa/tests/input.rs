@@ -0,0 +1,184 @@
+mod common;
+
+use common::{cisc, risc};
+use isa::{
+    cisc,
+    memory::Memory,
+    risc,
+    shared::{Access, Register, TrapReason, WatchKind},
+};
+
+const INPUT: &str = "42\n  -7 \n2.5\r\ntrue\nhello world\n";
+
+fn risc_run(source: &str, input: &str) -> (Result<[u64; 5], TrapReason>, Memory) {
+    let blocks = risc(source);
+    let mut vm = risc::vm::VM::new(&blocks, input.as_bytes(), Vec::new(), Memory::flat(1024));
+    let status = vm.interpret();
+    let result = match status.trap() {
+        Some(err) => Err(err.reason.clone()),
+        None => Ok(results(|reg| vm.registers().get(reg))),
+    };
+    (result, vm.memory().clone())
+}
+
+fn cisc_run(source: &str, input: &str) -> (Result<[u64; 5], TrapReason>, Memory) {
+    let blocks = cisc(source);
+    let mut vm = cisc::vm::VM::new(&blocks, input.as_bytes(), Vec::new(), Memory::flat(1024));
+    let status = vm.interpret();
+    let result = match status.trap() {
+        Some(err) => Err(err.reason.clone()),
+        None => Ok(results(|reg| vm.registers().get(reg))),
+    };
+    (result, vm.memory().clone())
+}
+
+// The programs below copy each syscall's result to R10 onwards
+fn results(get: impl Fn(&Register) -> u64) -> [u64; 5] {
+    [
+        get(&Register::R10),
+        get(&Register::R11),
+        get(&Register::R12),
+        get(&Register::R13),
+        get(&Register::R14),
+    ]
+}
+
+const EXPECTED: [u64; 5] = [42, -7i64 as u64, 0x4004_0000_0000_0000, u64::MAX, 4];
+
+#[test]
+fn risc_reads_one_value_per_line() {
+    let source = "main:
+          rega %9 6
+          syscall %9
+          copy %10 %1
+          rega %9 7
+          syscall %9
+          copy %11 %1
+          rega %9 8
+          syscall %9
+          copy %12 %1
+          rega %9 9
+          syscall %9
+          copy %13 %1
+          rega %1 64
+          rega %2 4
+          rega %9 10
+          syscall %9
+          copy %14 %1
+          rega %18 18446744073709551615";
+
+    let (result, memory) = risc_run(source, INPUT);
+    assert_eq!(result, Ok(EXPECTED));
+    // The rest of the line is discarded
+    assert_eq!(memory.to_vec(64..69), Ok(b"hell\0".to_vec()));
+}
+
+#[test]
+fn cisc_reads_one_value_per_line() {
+    let source = "main:
+          syscall 6
+          move %10 %1
+          syscall 7
+          move %11 %1
+          syscall 8
+          move %12 %1
+          syscall 9
+          move %13 %1
+          move %1 64
+          move %2 4
+          syscall 10
+          move %14 %1
+          move %18 18446744073709551615";
+
+    let (result, memory) = cisc_run(source, INPUT);
+    assert_eq!(result, Ok(EXPECTED));
+    assert_eq!(memory.to_vec(64..69), Ok(b"hell\0".to_vec()));
+}
+
+#[test]
+fn bad_input_traps() {
+    let read_u64 = "main:\n  rega %9 6\n  syscall %9\n  rega %18 18446744073709551615";
+    assert_eq!(risc_run(read_u64, "").0, Err(TrapReason::InputFailed));
+    assert_eq!(
+        risc_run(read_u64, " -1 \n").0,
+        Err(TrapReason::InvalidInput("-1".into()))
+    );
+
+    let read_bool = "main:\n  syscall 9\n  move %18 18446744073709551615";
+    assert_eq!(
+        cisc_run(read_bool, "yes\n").0,
+        Err(TrapReason::InvalidInput("yes".into()))
+    );
+}
+
+#[test]
+fn read_line_returns_0_once_the_input_ends() {
+    let source = "main:
+          rega %1 64
+          rega %2 8
+          rega %9 10
+          syscall %9
+          copy %10 %1
+          rega %18 18446744073709551615";
+    let (result, _) = risc_run(source, "");
+    assert_eq!(result.unwrap()[0], 0);
+}
+
+#[test]
+fn risc_read_line_stores_like_store_instructions() {
+    let blocks = risc(
+        "main:
+          rega %1 64
+          rega %2 11
+          rega %9 10
+          syscall %9
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc::vm::VM::new(
+        &blocks,
+        "hello world\n".as_bytes(),
+        Vec::new(),
+        Memory::flat(1024),
+    );
+    vm.record_history(true);
+    vm.add_watchpoint(74..75, WatchKind::Write);
+    for _ in 0..3 {
+        assert_eq!(vm.step(), Ok(None));
+    }
+
+    // 11 bytes take an 8, a 2 and a 1 byte store
+    let hit = vm.step().unwrap().unwrap();
+    assert_eq!((hit.adr, hit.size, hit.access), (74, 1, Access::Write));
+    assert_eq!(hit.new_value, u64::from(b'd'));
+    assert_eq!(vm.perf().stores, 3);
+    assert_eq!(vm.memory().to_vec(64..75), Ok(b"hello world".to_vec()));
+
+    assert!(vm.step_back());
+    assert_eq!(vm.rip(), 3);
+    assert_eq!(vm.memory().to_vec(64..75), Ok(vec![0; 11]));
+}
+
+#[test]
+fn cisc_read_line_can_be_stepped_back() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move %2 8
+          syscall 10
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc::vm::VM::new(
+        &blocks,
+        "hello\n".as_bytes(),
+        Vec::new(),
+        Memory::flat(1024),
+    );
+    vm.record_history(true);
+    vm.interpret();
+    assert_eq!(vm.memory().to_vec(64..69), Ok(b"hello".to_vec()));
+
+    assert!(vm.step_back());
+    assert!(vm.step_back());
+    assert_eq!(vm.rip(), 2);
+    assert_eq!(vm.memory().to_vec(64..69), Ok(vec![0; 5]));
+}
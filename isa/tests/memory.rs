@@ -0,0 +1,111 @@
+mod common;
+
+use common::{cisc, risc};
+use isa::{
+    cisc,
+    memory::{Memory, PAGE_SIZE},
+    risc,
+    shared::{Register, TrapReason},
+};
+use std::io;
+
+// Writes that start in one page and end in the next, plus a zero write to an
+// untouched page
+fn exercise(memory: &mut Memory) {
+    memory.write(PAGE_SIZE - 3, &[1, 2, 3, 4, 5, 6]).unwrap();
+    memory.write_uint(3 * PAGE_SIZE, 8, 0).unwrap();
+    memory.write_uint(100, 2, 0xabcd).unwrap();
+
+    assert_eq!(
+        memory.to_vec(PAGE_SIZE - 4..PAGE_SIZE + 4).unwrap(),
+        [0, 1, 2, 3, 4, 5, 6, 0]
+    );
+    assert_eq!(memory.read_uint(3 * PAGE_SIZE, 8).unwrap(), 0);
+    assert_eq!(memory.read_uint(100, 2).unwrap(), 0xabcd);
+    assert_eq!(memory.read_uint(100, 1).unwrap(), 0xcd);
+    assert_eq!(memory.read_uint(101, 1).unwrap(), 0xab);
+    assert_eq!(memory.read_uint(100, 4).unwrap(), 0xabcd);
+}
+
+#[test]
+fn flat_and_sparse_memory_agree() {
+    let mut flat = Memory::flat(4 * PAGE_SIZE);
+    exercise(&mut flat);
+
+    let mut sparse = Memory::sparse(4 * PAGE_SIZE);
+    exercise(&mut sparse);
+    assert_eq!(sparse.size(), flat.size());
+    assert_eq!(
+        sparse.to_vec(0..sparse.size()).unwrap(),
+        flat.to_vec(0..flat.size()).unwrap()
+    );
+}
+
+#[test]
+fn sparse_memory_only_allocates_written_pages() {
+    let mut memory = Memory::sparse(1 << 48);
+    memory.write_uint(1 << 40, 8, 0).unwrap();
+    assert_eq!(
+        format!("{:?}", memory),
+        "Memory { size: 281474976710656, resident_pages: 0 }"
+    );
+
+    memory.write_uint(1 << 40, 8, 7).unwrap();
+    memory.write_uint((1 << 40) + 8, 8, 7).unwrap();
+    assert_eq!(
+        format!("{:?}", memory),
+        "Memory { size: 281474976710656, resident_pages: 1 }"
+    );
+}
+
+#[test]
+fn accesses_past_the_end_are_out_of_bounds() {
+    for memory in &mut [Memory::flat(64), Memory::sparse(64)] {
+        assert_eq!(memory.read_uint(56, 8), Ok(0));
+        assert_eq!(
+            memory.read_uint(57, 8),
+            Err(TrapReason::MemoryOutOfBounds(57))
+        );
+        assert_eq!(
+            memory.write_uint(63, 2, 0),
+            Err(TrapReason::MemoryOutOfBounds(63))
+        );
+        // The end of the access overflows rather than wrapping to the start
+        assert_eq!(
+            memory.read_uint(u64::MAX - 3, 8),
+            Err(TrapReason::MemoryOutOfBounds(u64::MAX - 3))
+        );
+    }
+}
+
+const FAR: &str = "1099511627776";
+
+#[test]
+fn vms_address_large_sparse_memory() {
+    let blocks = risc(&format!(
+        "main:
+          rega %1 {}
+          rega %2 42
+          store %1 %2
+          load %3 %1
+          rega %18 18446744073709551615",
+        FAR
+    ));
+    let mut vm = risc::vm::VM::new(&blocks, io::empty(), Vec::new(), Memory::sparse(1 << 48));
+    assert!(vm.interpret().trap().is_none());
+    assert_eq!(vm.registers().get(&Register::R3), 42);
+    assert_eq!(vm.memory().read_uint(1 << 40, 8), Ok(42));
+
+    let blocks = cisc(&format!(
+        "main:
+          move %1 {}
+          move [%1] 42
+          move %3 [%1]
+          move %18 18446744073709551615",
+        FAR
+    ));
+    let mut vm = cisc::vm::VM::new(&blocks, io::empty(), Vec::new(), Memory::sparse(1 << 48));
+    assert!(vm.interpret().trap().is_none());
+    assert_eq!(vm.registers().get(&Register::R3), 42);
+    assert_eq!(vm.memory().read_uint(1 << 40, 8), Ok(42));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::cost::PerfCounters;
+
+// The branch's true target is the next instruction, which still counts as taken
+const EXPECTED: PerfCounters = PerfCounters {
+    insts_retired: 8,
+    // Moves and the jump cost 1, branches 2 and each memory access 2 on top
+    cycles: 14,
+    loads: 1,
+    stores: 1,
+    branches_taken: 2,
+    branches_not_taken: 1,
+    syscalls: 0,
+};
+
+#[test]
+fn risc_counts_branches_by_their_outcome() {
+    let blocks = risc(
+        "main:
+          rega %1 1
+          branch %1 @next @end
+        next:
+          rega %2 64
+          store %2 %1
+          load %3 %2
+          cjump %0 @end
+          jump @end
+        end:
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    vm.interpret();
+    assert_eq!(vm.perf(), EXPECTED);
+}
+
+#[test]
+fn cisc_counts_branches_by_their_outcome() {
+    let blocks = cisc(
+        "main:
+          move %1 1
+          branch %1 @next @end
+        next:
+          move %2 64
+          move [%2] %1
+          move %3 [%2]
+          cjump %0 @end
+          jump @end
+        end:
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    vm.interpret();
+    assert_eq!(vm.perf(), EXPECTED);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{risc, risc_vm};
+use isa::{
+    risc::pipeline::{simulate, HazardKind, PipelineConfig},
+    shared::Register,
+};
+
+const FORWARDING: PipelineConfig = PipelineConfig { forwarding: true };
+const NO_FORWARDING: PipelineConfig = PipelineConfig { forwarding: false };
+
+#[test]
+fn independent_insts_only_pay_for_filling_the_pipeline() {
+    let blocks = risc(
+        "main:
+          rega %1 1
+          rega %2 2
+          rega %3 3
+          rega %18 18446744073709551615",
+    );
+    let report = simulate(&mut risc_vm(&blocks), NO_FORWARDING).unwrap();
+    assert!(report.hazards.is_empty());
+    assert_eq!(report.insts(), 4);
+    // The last instruction leaves WB four cycles after it was fetched
+    assert_eq!(report.cycles, 8);
+    assert_eq!(report.cpi(), 2.0);
+}
+
+#[test]
+fn load_use_stalls_even_with_forwarding() {
+    let blocks = risc(
+        "main:
+          rega %2 64
+          load %1 %2
+          uadd %3 %1 %1
+          rega %18 18446744073709551615",
+    );
+
+    let forwarded = simulate(&mut risc_vm(&blocks), FORWARDING).unwrap();
+    assert_eq!(forwarded.data_stall_cycles, 1);
+    // Without forwarding the load also waits for its address
+    let stalled = simulate(&mut risc_vm(&blocks), NO_FORWARDING).unwrap();
+    assert_eq!(stalled.data_stall_cycles, 4);
+    assert_eq!(stalled.cycles, forwarded.cycles + 3);
+    assert!(stalled.cpi() > forwarded.cpi());
+
+    let hazard = forwarded.hazards.last().unwrap();
+    assert_eq!(hazard.seq, 2);
+    assert_eq!(
+        hazard.kind,
+        HazardKind::Data {
+            reg: Register::R1,
+            forwarded: true
+        }
+    );
+    assert_eq!(hazard.stall_cycles, 1);
+}
+
+#[test]
+fn taken_branches_flush_the_younger_insts() {
+    let blocks = risc(
+        "main:
+          jump @end
+          rega %1 1
+        end:
+          rega %18 18446744073709551615",
+    );
+    let report = simulate(&mut risc_vm(&blocks), FORWARDING).unwrap();
+    assert_eq!(report.control_stall_cycles, 2);
+    assert_eq!(report.hazards[0].kind, HazardKind::Control);
+    assert_eq!(report.cycles, 8);
+}
+
+#[test]
+fn syscalls_write_r1() {
+    let blocks = risc(
+        "main:
+          rega %1 5
+          syscall %0
+          copy %2 %1
+          rega %18 18446744073709551615",
+    );
+    let report = simulate(&mut risc_vm(&blocks), NO_FORWARDING).unwrap();
+    // `copy` waits for the syscall to leave WB, not for `rega`
+    let hazard = report.hazards.last().unwrap();
+    assert_eq!(hazard.seq, 2);
+    assert_eq!(hazard.stall_cycles, 2);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::predictor::{
+    BranchCounts, BranchPredictor, Gshare, OneBit, StaticTaken, TwoBit, MAX_INDEX_BITS,
+};
+
+// Feeds `outcomes` for the branch at `rip` and returns how many were mispredicted
+fn mispredictions(predictor: &mut dyn BranchPredictor, rip: u64, outcomes: &[bool]) -> usize {
+    outcomes
+        .iter()
+        .filter(|taken| {
+            let predicted = predictor.predict(rip);
+            predictor.update(rip, **taken);
+            predicted != **taken
+        })
+        .count()
+}
+
+#[test]
+fn two_bit_counters_tolerate_a_single_surprise() {
+    let mut predictor = TwoBit::new(4);
+    // Starts weakly not taken
+    assert_eq!(mispredictions(&mut predictor, 3, &[true, true, true]), 1);
+    assert_eq!(mispredictions(&mut predictor, 3, &[false, true]), 1);
+
+    // A one bit predictor flips on every change of direction
+    let mut predictor = OneBit::new(4);
+    assert_eq!(mispredictions(&mut predictor, 3, &[true, true, true]), 1);
+    assert_eq!(mispredictions(&mut predictor, 3, &[false, true]), 2);
+}
+
+#[test]
+fn branches_alias_on_the_low_bits() {
+    let mut predictor = OneBit::new(1);
+    predictor.update(0, true);
+    assert!(predictor.predict(2));
+    assert!(!predictor.predict(1));
+}
+
+#[test]
+fn gshare_learns_alternating_branches() {
+    let alternating = [true, false].repeat(16);
+
+    let mut predictor = TwoBit::new(4);
+    assert!(mispredictions(&mut predictor, 0, &alternating) >= 16);
+
+    // Only the warm up is mispredicted once the history tells the two apart
+    let mut predictor = Gshare::new(4);
+    assert!(mispredictions(&mut predictor, 0, &alternating) <= 4);
+}
+
+#[test]
+#[should_panic(expected = "history can be at most 24 bits, found 64")]
+fn gshare_rejects_oversized_histories() {
+    Gshare::new(64);
+}
+
+#[test]
+#[should_panic(expected = "index can be at most 24 bits, found 25")]
+fn tables_reject_oversized_indices() {
+    TwoBit::new(MAX_INDEX_BITS + 1);
+}
+
+// Branches are taken when their condition is 1, so the loop's branch falls
+// through three times then leaves the loop
+const LOOP_COUNTS: BranchCounts = BranchCounts {
+    executed: 4,
+    taken: 1,
+    mispredicted: 3,
+};
+
+#[test]
+fn risc_records_outcomes_per_branch() {
+    let blocks = risc(
+        "main:
+          rega %1 5
+          rega %2 1
+        loop:
+          sub %1 %1 %2
+          branch %1 @end @loop
+        end:
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    assert!(vm.branch_stats().is_none());
+    vm.set_branch_predictor(Some(Rc::new(RefCell::new(StaticTaken))));
+    let status = vm.interpret();
+
+    let stats = status.branches.unwrap();
+    assert_eq!(stats.per_branch.keys().collect::<Vec<_>>(), [&3]);
+    assert_eq!(stats.overall(), LOOP_COUNTS);
+    assert_eq!(stats.overall().misprediction_rate(), 0.75);
+}
+
+#[test]
+fn cisc_records_outcomes_per_branch() {
+    let blocks = cisc(
+        "main:
+          move %1 5
+        loop:
+          sub %1 %1 1
+          branch %1 @end @loop
+        end:
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    vm.set_branch_predictor(Some(Rc::new(RefCell::new(StaticTaken))));
+    let status = vm.interpret();
+
+    let stats = status.branches.unwrap();
+    assert_eq!(stats.per_branch.keys().collect::<Vec<_>>(), [&2]);
+    assert_eq!(stats.overall(), LOOP_COUNTS);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::{
+    memory::Memory,
+    risc,
+    shared::{Register, TrapReason},
+    syscall::{SyscallContext, PRINT_U64},
+};
+use std::{cell::RefCell, io, rc::Rc};
+
+fn add(ctx: &mut SyscallContext<'_>) -> Result<(), TrapReason> {
+    let sum = ctx.arg(0)? + ctx.arg(1)?;
+    ctx.set_result(sum);
+    Ok(())
+}
+
+#[test]
+fn risc_dispatches_to_registered_handlers() {
+    let blocks = risc(
+        "main:
+          rega %1 2
+          rega %2 3
+          rega %3 100
+          syscall %3
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    vm.register_syscall(100, add);
+    let status = vm.interpret();
+    assert!(status.trap().is_none());
+    assert_eq!(vm.registers().get(&Register::R1), 5);
+    assert_eq!(status.perf.syscalls, 1);
+}
+
+#[test]
+fn cisc_dispatches_to_registered_handlers() {
+    let blocks = cisc(
+        "main:
+          move %1 2
+          move %2 3
+          syscall 100
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    vm.register_syscall(100, add);
+    assert!(vm.interpret().trap().is_none());
+    assert_eq!(vm.registers().get(&Register::R1), 5);
+}
+
+#[test]
+fn handlers_replace_built_in_syscalls() {
+    let blocks = risc(
+        "main:
+          rega %1 42
+          syscall %0
+          rega %18 18446744073709551615",
+    );
+    let printed = Rc::new(RefCell::new(Vec::new()));
+    let mut output = Vec::new();
+    let mut vm = risc::vm::VM::new(&blocks, io::empty(), &mut output, Memory::flat(1024));
+    let sink = Rc::clone(&printed);
+    vm.register_syscall(PRINT_U64, move |ctx: &mut SyscallContext<'_>| {
+        sink.borrow_mut().push(ctx.arg(0)?);
+        Ok(())
+    });
+    let status = vm.interpret();
+
+    assert!(status.trap().is_none());
+    drop(vm);
+    assert_eq!(*printed.borrow(), [42]);
+    assert!(output.is_empty());
+}
+
+#[test]
+fn arguments_past_r15_trap() {
+    let blocks = risc(
+        "main:
+          rega %15 7
+          rega %1 100
+          syscall %1
+          rega %18 18446744073709551615",
+    );
+
+    let mut vm = risc_vm(&blocks);
+    vm.register_syscall(100, |ctx: &mut SyscallContext<'_>| {
+        let last = ctx.arg(14)?;
+        ctx.set_result(last);
+        Ok(())
+    });
+    vm.interpret();
+    assert_eq!(vm.registers().get(&Register::R1), 7);
+
+    for index in [15, 17, u8::MAX].iter().copied() {
+        let mut vm = risc_vm(&blocks);
+        vm.register_syscall(100, move |ctx: &mut SyscallContext<'_>| {
+            ctx.arg(index).map(|_| ())
+        });
+        assert_eq!(
+            vm.interpret().trap().unwrap().reason,
+            TrapReason::InvalidSyscallArg(index)
+        );
+    }
+}
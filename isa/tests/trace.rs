@@ -0,0 +1,99 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::trace::{TraceFormat, Tracer};
+
+const RISC_STORE: &str = "main:
+  rega %1 64
+  store %1 %1
+  rega %18 18446744073709551615";
+
+fn risc_trace(format: TraceFormat) -> String {
+    let blocks = risc(RISC_STORE);
+    let mut trace = Vec::new();
+    {
+        let mut vm = risc_vm(&blocks);
+        vm.set_tracer(Some(Tracer::new(&mut trace, format)));
+        vm.interpret();
+    }
+    String::from_utf8(trace).unwrap()
+}
+
+#[test]
+fn risc_text_trace() {
+    assert_eq!(
+        risc_trace(TraceFormat::Text),
+        "     0: rega %1 64                  %1 = 0x40
+     1: store %1 %1                 write [0x40] 0x0 -> 0x40
+     2: rega %18 18446744073709551615
+"
+    );
+}
+
+#[test]
+fn risc_json_lines_trace() {
+    assert_eq!(
+        risc_trace(TraceFormat::JsonLines),
+        r#"{"rip":0,"asm":"rega %1 64","registers":[{"reg":1,"old":0,"new":64}],"memory":[]}
+{"rip":1,"asm":"store %1 %1","registers":[],"memory":[{"adr":64,"size":8,"access":"write","old":0,"new":64}]}
+{"rip":2,"asm":"rega %18 18446744073709551615","registers":[],"memory":[]}
+"#
+    );
+}
+
+#[test]
+fn cisc_trace_shows_both_sides_of_memory_to_memory_moves() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move [%1] 5
+          move [%1] [%1]
+          move %18 18446744073709551615",
+    );
+    let mut trace = Vec::new();
+    {
+        let mut vm = cisc_vm(&blocks);
+        vm.set_tracer(Some(Tracer::new(&mut trace, TraceFormat::Text)));
+        vm.interpret();
+    }
+
+    let trace = String::from_utf8(trace).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[2],
+        "     2: move [%1] [%1]              read [0x40] 0x5, write [0x40] 0x5 -> 0x5"
+    );
+}
+
+#[test]
+fn tracing_can_be_turned_off_again() {
+    let blocks = risc(RISC_STORE);
+    let mut trace = Vec::new();
+    {
+        let mut vm = risc_vm(&blocks);
+        vm.set_tracer(Some(Tracer::new(&mut trace, TraceFormat::Text)));
+        vm.step().unwrap();
+        vm.set_tracer(None);
+        vm.interpret();
+    }
+    assert_eq!(String::from_utf8(trace).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn cloned_vms_trace_to_the_same_sink() {
+    let blocks = risc(RISC_STORE);
+    let mut trace = Vec::new();
+    {
+        let mut vm = risc_vm(&blocks);
+        vm.set_tracer(Some(Tracer::new(&mut trace, TraceFormat::Text)));
+        vm.step().unwrap();
+
+        let mut fork = vm.clone();
+        fork.interpret();
+        vm.interpret();
+        assert_eq!(fork.memory().read_uint(64, 8), Ok(64));
+        assert_eq!(vm.memory().read_uint(64, 8), Ok(64));
+    }
+    assert_eq!(String::from_utf8(trace).unwrap().lines().count(), 5);
+}
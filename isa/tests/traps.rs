@@ -0,0 +1,97 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::shared::{HaltReason, Register, TrapReason};
+
+#[test]
+fn risc_traps_keep_the_state_before_the_faulting_instruction() {
+    let blocks = risc(
+        "main:
+          rega %1 7
+          rega %2 0
+          sdiv %3 %1 %2",
+    );
+    let status = risc_vm(&blocks).interpret();
+
+    assert_eq!(status.code, 1);
+    let err = status.trap().unwrap();
+    assert_eq!(err.reason, TrapReason::DivisionByZero);
+    assert_eq!(err.inst_index, 2);
+    assert_eq!(err.registers.get(&Register::R1), 7);
+    assert_eq!(err.registers.get(&Register::R3), 0);
+    assert_eq!(err.registers.get(&Register::Rip), 2);
+}
+
+#[test]
+fn risc_trap_reasons() {
+    let cases = [
+        (
+            "main:\n  rega %1 1020\n  load %2 %1",
+            TrapReason::MemoryOutOfBounds(1020),
+        ),
+        (
+            "main:\n  rega %1 999\n  syscall %1",
+            TrapReason::UnknownSyscall(999),
+        ),
+        (
+            "main:\n  jump @nowhere",
+            TrapReason::UndefinedLabel("nowhere".into()),
+        ),
+        ("main:\n  rega %1 1", TrapReason::RipOutOfBounds(1)),
+        (
+            "main:\n  rega %1 0\n  urem %1 %1 %1",
+            TrapReason::DivisionByZero,
+        ),
+    ];
+
+    for (source, reason) in cases.iter() {
+        let blocks = risc(source);
+        let status = risc_vm(&blocks).interpret();
+        match &status.reason {
+            HaltReason::Trapped(err) => assert_eq!(&err.reason, reason, "{}", source),
+            reason => panic!("`{}` halted with {:?}", source, reason),
+        }
+    }
+}
+
+#[test]
+fn cisc_traps_keep_the_state_before_the_faulting_instruction() {
+    let blocks = cisc(
+        "main:
+          move %1 7
+          move %2 2000
+          move %3 [%2]",
+    );
+    let status = cisc_vm(&blocks).interpret();
+
+    assert_eq!(status.code, 1);
+    let err = status.trap().unwrap();
+    assert_eq!(err.reason, TrapReason::MemoryOutOfBounds(2000));
+    assert_eq!(err.inst_index, 2);
+    assert_eq!(err.registers.get(&Register::R1), 7);
+    assert_eq!(err.registers.get(&Register::R2), 2000);
+    assert_eq!(err.registers.get(&Register::R3), 0);
+}
+
+#[test]
+fn cisc_trap_reasons() {
+    let cases = [
+        ("main:\n  move 1 %1", TrapReason::ImmediateDestination),
+        ("main:\n  sdiv %1 7 0", TrapReason::DivisionByZero),
+        ("main:\n  syscall 999", TrapReason::UnknownSyscall(999)),
+        (
+            "main:\n  jump @nowhere",
+            TrapReason::UndefinedLabel("nowhere".into()),
+        ),
+        ("main:\n  move %1 1", TrapReason::RipOutOfBounds(1)),
+    ];
+
+    for (source, reason) in cases.iter() {
+        let blocks = cisc(source);
+        let status = cisc_vm(&blocks).interpret();
+        match &status.reason {
+            HaltReason::Trapped(err) => assert_eq!(&err.reason, reason, "{}", source),
+            reason => panic!("`{}` halted with {:?}", source, reason),
+        }
+    }
+}
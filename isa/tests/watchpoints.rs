@@ -0,0 +1,107 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::shared::{Access, Stop, WatchKind, WatchpointHit};
+
+const RISC_STORE_LOAD: &str = "main:
+  rega %1 64
+  rega %2 42
+  store %1 %2
+  load %3 %1
+  rega %18 18446744073709551615";
+
+#[test]
+fn risc_watchpoints_stop_after_the_access() {
+    let blocks = risc(RISC_STORE_LOAD);
+    let mut vm = risc_vm(&blocks);
+    vm.add_watchpoint(64..72, WatchKind::Write);
+
+    assert_eq!(
+        vm.run_until_breakpoint().unwrap(),
+        Stop::Watchpoint(WatchpointHit {
+            inst_index: 2,
+            adr: 64,
+            size: 8,
+            access: Access::Write,
+            old_value: 0,
+            new_value: 42,
+        })
+    );
+    assert_eq!(vm.rip(), 3);
+
+    // The load isn't watched
+    assert!(matches!(
+        vm.run_until_breakpoint().unwrap(),
+        Stop::Halted(_)
+    ));
+}
+
+#[test]
+fn risc_watchpoints_match_kind_and_overlap() {
+    let blocks = risc(RISC_STORE_LOAD);
+    let mut vm = risc_vm(&blocks);
+    // Only overlaps the last byte of the stored word
+    vm.add_watchpoint(71..80, WatchKind::ReadWrite);
+
+    let mut hits = Vec::new();
+    while !vm.is_halted() {
+        if let Some(hit) = vm.step().unwrap() {
+            hits.push((hit.inst_index, hit.access, hit.new_value));
+        }
+    }
+    assert_eq!(hits, [(2, Access::Write, 42), (3, Access::Read, 42)]);
+
+    let mut vm = risc_vm(&blocks);
+    vm.add_watchpoint(72..80, WatchKind::ReadWrite);
+    vm.add_watchpoint(64..72, WatchKind::Read);
+    assert!(vm.remove_watchpoint(&(72..80)));
+    assert!(!vm.remove_watchpoint(&(72..80)));
+    match vm.run_until_breakpoint().unwrap() {
+        Stop::Watchpoint(hit) => assert_eq!((hit.inst_index, hit.access), (3, Access::Read)),
+        stop => panic!("expected a watchpoint hit, stopped with {:?}", stop),
+    }
+}
+
+#[test]
+fn cisc_memory_to_memory_moves_hit_read_and_write_watchpoints() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move %2 128
+          move [%1] 7
+          move [%2] [%1]
+          move %18 18446744073709551615",
+    );
+
+    let mut vm = cisc_vm(&blocks);
+    vm.add_watchpoint(64..72, WatchKind::Read);
+    assert_eq!(
+        vm.run_until_breakpoint().unwrap(),
+        Stop::Watchpoint(WatchpointHit {
+            inst_index: 3,
+            adr: 64,
+            size: 8,
+            access: Access::Read,
+            old_value: 7,
+            new_value: 7,
+        })
+    );
+
+    let mut vm = cisc_vm(&blocks);
+    vm.add_watchpoint(128..136, WatchKind::Write);
+    assert_eq!(
+        vm.run_until_breakpoint().unwrap(),
+        Stop::Watchpoint(WatchpointHit {
+            inst_index: 3,
+            adr: 128,
+            size: 8,
+            access: Access::Write,
+            old_value: 0,
+            new_value: 7,
+        })
+    );
+    assert!(matches!(
+        vm.run_until_breakpoint().unwrap(),
+        Stop::Halted(_)
+    ));
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::{cisc, cisc_vm, risc, risc_vm};
+use isa::{
+    memory::Memory,
+    shared::{Register, Stop, WatchKind},
+};
+
+// Every byte of the value at 64 has its top bit set, none of the ones at 72 do
+fn fill(memory: &mut Memory) {
+    memory.write_uint(64, 8, 0xf0f1_f2f3_f4f5_f6f7).unwrap();
+    memory.write_uint(72, 8, 0x0102_0304_0506_0708).unwrap();
+    for adr in [80, 88, 96].iter() {
+        memory.write_uint(*adr, 8, u64::MAX).unwrap();
+    }
+}
+
+// R2 to R8 load 64 at each width, zero extended then sign extended, R9 to R11
+// sign extend 72
+const LOADED: [(Register, u64); 10] = [
+    (Register::R2, 0xf7),
+    (Register::R3, 0xffff_ffff_ffff_fff7),
+    (Register::R4, 0xf6f7),
+    (Register::R5, 0xffff_ffff_ffff_f6f7),
+    (Register::R6, 0xf4f5_f6f7),
+    (Register::R7, 0xffff_ffff_f4f5_f6f7),
+    (Register::R8, 0xf0f1_f2f3_f4f5_f6f7),
+    (Register::R9, 0x08),
+    (Register::R10, 0x0708),
+    (Register::R11, 0x0506_0708),
+];
+
+// 0x1234 stored at 80, 88 and 96 as 16, 8 and 32 bits, leaving the rest of
+// each word alone
+const STORED: [(u64, u64); 3] = [
+    (80, 0xffff_ffff_ffff_1234),
+    (88, 0xffff_ffff_ffff_ff34),
+    (96, 0xffff_ffff_0000_1234),
+];
+
+#[test]
+fn risc_loads_extend_and_stores_truncate() {
+    let blocks = risc(
+        "main:
+          rega %1 64
+          load8 %2 %1
+          load8s %3 %1
+          load16 %4 %1
+          load16s %5 %1
+          load32 %6 %1
+          load32s %7 %1
+          load %8 %1
+          rega %1 72
+          load8s %9 %1
+          load16s %10 %1
+          load32s %11 %1
+          rega %12 4660
+          rega %1 80
+          store16 %1 %12
+          rega %1 88
+          store8 %1 %12
+          rega %1 96
+          store32 %1 %12
+          rega %18 18446744073709551615",
+    );
+    let mut vm = risc_vm(&blocks);
+    fill(vm.memory_mut());
+    assert!(vm.interpret().trap().is_none());
+
+    for (reg, value) in LOADED.iter() {
+        assert_eq!(vm.registers().get(reg), *value, "{:?}", reg);
+    }
+    for (adr, value) in STORED.iter() {
+        assert_eq!(vm.memory().read_uint(*adr, 8), Ok(*value), "{}", adr);
+    }
+}
+
+#[test]
+fn cisc_moves_extend_and_truncate() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move8 %2 [%1]
+          move8s %3 [%1]
+          move16 %4 [%1]
+          move16s %5 [%1]
+          move32 %6 [%1]
+          move32s %7 [%1]
+          move %8 [%1]
+          move %1 72
+          move8s %9 [%1]
+          move16s %10 [%1]
+          move32s %11 [%1]
+          move %1 80
+          move16 [%1] 4660
+          move %1 88
+          move8 [%1] 4660
+          move %1 96
+          move32 [%1] 4660
+          move %12 128
+          move8s %13 %12
+          move8 %14 %12
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    fill(vm.memory_mut());
+    assert!(vm.interpret().trap().is_none());
+
+    for (reg, value) in LOADED.iter() {
+        assert_eq!(vm.registers().get(reg), *value, "{:?}", reg);
+    }
+    for (adr, value) in STORED.iter() {
+        assert_eq!(vm.memory().read_uint(*adr, 8), Ok(*value), "{}", adr);
+    }
+    // Register to register moves extend as well
+    assert_eq!(vm.registers().get(&Register::R13), 0xffff_ffff_ffff_ff80);
+    assert_eq!(vm.registers().get(&Register::R14), 0x80);
+}
+
+#[test]
+fn cisc_sub_word_moves_report_their_size() {
+    let blocks = cisc(
+        "main:
+          move %1 64
+          move16 [%1] 65535
+          move %18 18446744073709551615",
+    );
+    let mut vm = cisc_vm(&blocks);
+    vm.add_watchpoint(65..66, WatchKind::Write);
+
+    match vm.run_until_breakpoint().unwrap() {
+        Stop::Watchpoint(hit) => {
+            assert_eq!((hit.adr, hit.size, hit.new_value), (64, 2, 65535));
+        }
+        stop => panic!("expected a watchpoint hit, stopped with {:?}", stop),
+    }
+}
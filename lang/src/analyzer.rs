@@ -51,6 +51,41 @@ impl<'a> Analyzer<'a> {
             );
         }
 
+        // Read a value from the program's input, one per line
+        for type_name in &["u64", "i64", "f64", "bool"] {
+            namespace.insert(
+                format!("read_{}", type_name),
+                ast::Type {
+                    span: 0..0,
+                    kind: ast::FnType {
+                        name: format!("read_{}", type_name),
+                        parameters: Vec::new(),
+                        returns: Some(Box::new(ast::Type {
+                            span: 0..0,
+                            kind: typespace[*type_name].clone(),
+                        })),
+                    }
+                    .into(),
+                },
+            );
+        }
+
+        namespace.insert(
+            "exit".into(),
+            ast::Type {
+                span: 0..0,
+                kind: ast::FnType {
+                    name: "exit".into(),
+                    parameters: vec![ast::Type {
+                        span: 0..0,
+                        kind: ast::PrimType::Int(32).into(),
+                    }],
+                    returns: None,
+                }
+                .into(),
+            },
+        );
+
         Self {
             file,
             namespace,
@@ -179,7 +214,10 @@ impl<'a> Analyzer<'a> {
 
             ast::StmtKind::Let(let_stmt) => {
                 if let Some(typ) = &mut let_stmt.typ {
+                    self.analyze_type(typ)?;
                     self.analyze_expr(&mut let_stmt.init)?;
+                    coerce_literal(&mut let_stmt.init, typ);
+                    check_int_lit(self.file, &let_stmt.init)?;
 
                     // e.g. let x int = 32
                     if let Some(init_type) = &let_stmt.init.typ {
@@ -199,6 +237,7 @@ impl<'a> Analyzer<'a> {
                 } else {
                     // e.g. let x = 34
                     self.analyze_expr(&mut let_stmt.init)?;
+                    check_int_lit(self.file, &let_stmt.init)?;
                     let_stmt.typ = let_stmt.init.typ.clone();
                 }
 
@@ -281,6 +320,8 @@ impl<'a> Analyzer<'a> {
                 if let Some(current_fnc) = &self.within_function {
                     if let Some(value) = &mut return_stmt.value {
                         if let Some(return_type) = &current_fnc.return_type {
+                            coerce_literal(value, return_type);
+                            check_int_lit(self.file, value)?;
                             if let Some(value_type) = &value.typ {
                                 if !self.type_eq(value_type, return_type) {
                                     return Err(Error {
@@ -423,6 +464,15 @@ impl<'a> Analyzer<'a> {
                     self.analyze_expr(&mut binary_expr.left)?;
                     self.analyze_expr(&mut binary_expr.right)?;
 
+                    if let Some(left_expr_type) = binary_expr.left.typ.clone() {
+                        coerce_literal(&mut binary_expr.right, &left_expr_type);
+                    }
+                    if let Some(right_expr_type) = binary_expr.right.typ.clone() {
+                        coerce_literal(&mut binary_expr.left, &right_expr_type);
+                    }
+                    check_int_lit(self.file, &binary_expr.left)?;
+                    check_int_lit(self.file, &binary_expr.right)?;
+
                     if let Some(left_expr_type) = &binary_expr.left.typ {
                         if let Some(right_expr_type) = &binary_expr.right.typ {
                             if !self.type_eq(right_expr_type, left_expr_type) {
@@ -513,9 +563,11 @@ impl<'a> Analyzer<'a> {
                 if let Some(callee_type) = &call_expr.callee.typ {
                     if let ast::TypeKind::Fn(fn_type) = &callee_type.kind {
                         // Validate arguments
-                        for (i, arg) in call_expr.args.iter().enumerate() {
+                        for (i, arg) in call_expr.args.iter_mut().enumerate() {
                             if arg.typ.is_some() {
                                 let param_type = &fn_type.parameters[i];
+                                coerce_literal(arg, param_type);
+                                check_int_lit(self.file, arg)?;
                                 if let Some(arg_type) = &arg.typ {
                                     if !self.type_eq(arg_type, param_type) {
                                         return Err(Error {
@@ -557,6 +609,34 @@ impl<'a> Analyzer<'a> {
             }
             ast::ExprKind::StructLit(struct_lit) => {
                 self.analyze_type(&mut struct_lit.typ)?;
+
+                if let ast::TypeKind::Struct(struct_type) = &struct_lit.typ.kind {
+                    for (member_ident, member_value) in struct_lit.inits.iter_mut() {
+                        self.analyze_expr(member_value)?;
+
+                        let member_name = self.file.lexeme(&member_ident.span);
+                        let member_type = struct_type
+                            .members
+                            .iter()
+                            .find(|(name, _)| name == member_name)
+                            .map(|(_, member_type)| member_type);
+                        if let Some(member_type) = member_type {
+                            coerce_literal(member_value, member_type);
+                            check_int_lit(self.file, member_value)?;
+                        }
+                        if let (Some(member_type), Some(value_type)) =
+                            (member_type, &member_value.typ)
+                        {
+                            if !self.type_eq(value_type, member_type) {
+                                return Err(Error {
+                                    message: "invalid member type".into(),
+                                    span: member_value.span.clone(),
+                                });
+                            }
+                        }
+                    }
+                }
+
                 expr.typ = Some(struct_lit.typ.clone());
             }
             ast::ExprKind::Lit(lit) => {
@@ -600,6 +680,90 @@ impl<'a> Analyzer<'a> {
     }
 }
 
+// Number literals take on the type they're used as when it's compatible with
+// them, e.g. `1.5` in `let x f32 = 1.5` is an f32 rather than the default f64
+fn coerce_literal(expr: &mut ast::Expr, expected: &ast::Type) {
+    match &mut expr.kind {
+        ast::ExprKind::Lit(lit) => {
+            let compatible = matches!(
+                (&lit.token.kind, &expected.kind),
+                (
+                    token::TokenKind::Float,
+                    ast::TypeKind::Prim(ast::PrimType::Float(_))
+                ) | (
+                    token::TokenKind::Int,
+                    ast::TypeKind::Prim(ast::PrimType::Int(_))
+                ) | (
+                    token::TokenKind::Int,
+                    ast::TypeKind::Prim(ast::PrimType::UInt(_))
+                )
+            );
+            if compatible {
+                expr.typ = Some(expected.clone());
+            }
+        }
+        ast::ExprKind::Unary(unary_expr) if unary_expr.op.kind == token::TokenKind::Minus => {
+            coerce_literal(&mut unary_expr.expr, expected);
+            expr.typ = unary_expr.expr.typ.clone();
+        }
+        _ => {}
+    }
+}
+
+// Int literals have to fit the type they ended up with, `let x u8 = 256` is an
+// error rather than 0. A negated literal is checked as a whole so `-128` fits
+// an i8
+fn check_int_lit(file: &ast::File, expr: &ast::Expr) -> Result<(), Error> {
+    let (lit, negated) = match &expr.kind {
+        ast::ExprKind::Lit(lit) => (lit, false),
+        ast::ExprKind::Unary(unary_expr) if unary_expr.op.kind == token::TokenKind::Minus => {
+            match &unary_expr.expr.kind {
+                ast::ExprKind::Lit(lit) => (lit, true),
+                _ => return Ok(()),
+            }
+        }
+        _ => return Ok(()),
+    };
+    if lit.token.kind != token::TokenKind::Int {
+        return Ok(());
+    }
+
+    let (bits, signed) = match expr.typ.as_ref().map(|typ| &typ.kind) {
+        Some(ast::TypeKind::Prim(ast::PrimType::Int(bits))) => (*bits as u32, true),
+        Some(ast::TypeKind::Prim(ast::PrimType::UInt(bits))) => (*bits as u32, false),
+        _ => return Ok(()),
+    };
+    let (min, max) = if signed {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    } else {
+        (0, (1i128 << bits) - 1)
+    };
+
+    // Too large for a u64 is too large for any type
+    let value = file
+        .lexeme(&lit.token.span)
+        .parse::<u64>()
+        .ok()
+        .map(|value| {
+            if negated {
+                -(value as i128)
+            } else {
+                value as i128
+            }
+        });
+    match value {
+        Some(value) if min <= value && value <= max => Ok(()),
+        _ => Err(Error {
+            message: format!(
+                "integer literal is out of range for {}{}",
+                if signed { "i" } else { "u" },
+                bits
+            ),
+            span: expr.span.clone(),
+        }),
+    }
+}
+
 #[allow(dead_code)]
 pub fn analyze(file: &ast::File) -> Result<ast::File, Error> {
     let mut new_file = file.clone();
@@ -1,12 +1,29 @@
-use crate::{ast, token};
+use crate::{ast, codegen::Arithmetic, token};
 use isa::{
     cisc::inst::{self, Inst, Operand},
     shared::{Imm, Label, Register},
+    syscall,
 };
 use std::{borrow::Borrow, collections::HashMap, convert::TryInto};
 
+// Moves a value of `typ`, only touching as many bytes of memory as it takes
+fn move_inst(typ: &ast::TypeKind, dst: Operand, src: Operand) -> Inst {
+    match (super::size_of(typ), super::is_sign_extended(typ)) {
+        (1, false) => Inst::Move8(dst, src),
+        (1, true) => Inst::Move8S(dst, src),
+        (2, false) => Inst::Move16(dst, src),
+        (2, true) => Inst::Move16S(dst, src),
+        (4, false) => Inst::Move32(dst, src),
+        (4, true) => Inst::Move32S(dst, src),
+        _ => Inst::Move(dst, src),
+    }
+}
+
+type IntInst = fn(Operand, Operand, Operand) -> Inst;
+
 #[derive(Debug)]
 struct Generator<'a> {
+    arithmetic: Arithmetic,
     current_stack_offset: u64,
     available_tmp_registers: Vec<Register>,
     blocks: Vec<inst::Block>,
@@ -15,8 +32,49 @@ struct Generator<'a> {
 }
 
 impl<'a> Generator<'a> {
+    // Checked arithmetic swaps in the variant that traps on overflow
+    fn int_inst(&self, wrapping: IntInst, checked: IntInst) -> IntInst {
+        match self.arithmetic {
+            Arithmetic::Wrapping => wrapping,
+            Arithmetic::Checked => checked,
+        }
+    }
+
+    // Brings the integer in `reg` back to the width of `typ` with a sized move
+    // onto itself. In checked mode a result that didn't fit traps just like a
+    // 64-bit overflow
+    fn wrap_to_width(&mut self, typ: &ast::TypeKind, reg: Register, block: &mut inst::Block) {
+        if super::narrow_int(typ).is_none() {
+            return;
+        }
+
+        let unwrapped_reg = self.get_tmp_reg();
+        block
+            .insts
+            .push(Inst::Move(Operand::Data(unwrapped_reg), Operand::Data(reg)));
+        block
+            .insts
+            .push(move_inst(typ, Operand::Data(reg), Operand::Data(reg)));
+
+        if self.arithmetic == Arithmetic::Checked {
+            // Any bits that changed make adding the largest u64 overflow
+            block.insts.push(Inst::Xor(
+                Operand::Data(unwrapped_reg),
+                Operand::Data(unwrapped_reg),
+                Operand::Data(reg),
+            ));
+            block.insts.push(Inst::UAddChecked(
+                Operand::Data(unwrapped_reg),
+                Operand::Data(unwrapped_reg),
+                Operand::Imm(Imm::Int(u64::MAX)),
+            ));
+        }
+
+        self.make_reg_available(&unwrapped_reg);
+    }
+
     fn get_tmp_reg(&mut self) -> Register {
-        let tmp_reg = if let Some(tmp_reg) = self.available_tmp_registers.get(0) {
+        let tmp_reg = if let Some(tmp_reg) = self.available_tmp_registers.first() {
             *tmp_reg
         } else {
             // I'm unsure if it's actually possible to reach here
@@ -57,10 +115,12 @@ impl<'a> Generator<'a> {
                     panic!("can only generate assembly for functions with less than 8 parameters")
                 }
 
-                for (i, (param_ident, _)) in fn_decl.parameters.iter().enumerate() {
+                for (i, (param_ident, param_type)) in fn_decl.parameters.iter().enumerate() {
                     let param_reg = Register::from_id((i + 1).try_into().unwrap());
+                    let param_size = super::size_of(&param_type.kind);
 
-                    fn_init_block.insts.push(Inst::Move(
+                    fn_init_block.insts.push(move_inst(
+                        &param_type.kind,
                         Operand::Adr(Register::Rsp),
                         Operand::Data(param_reg),
                     ));
@@ -73,9 +133,9 @@ impl<'a> Generator<'a> {
                     fn_init_block.insts.push(Inst::UAdd(
                         Operand::Data(Register::Rsp),
                         Operand::Data(Register::Rsp),
-                        Operand::Imm(Imm::Int(8)),
+                        Operand::Imm(Imm::Int(param_size)),
                     ));
-                    self.current_stack_offset += 8;
+                    self.current_stack_offset += param_size;
                 }
 
                 self.blocks.push(fn_init_block);
@@ -106,6 +166,8 @@ impl<'a> Generator<'a> {
                 };
 
                 let initializer = self.gen_expression(&let_stmt.init, &mut block);
+                let init_type = &let_stmt.init.typ.as_ref().unwrap().kind;
+                let init_size = super::size_of(init_type);
                 let last_idx = self.blocks.len() - 1;
 
                 self.namespace.insert(
@@ -113,15 +175,17 @@ impl<'a> Generator<'a> {
                     self.current_stack_offset,
                 );
 
-                block
-                    .insts
-                    .push(Inst::Move(Operand::Adr(Register::Rsp), initializer));
+                block.insts.push(move_inst(
+                    init_type,
+                    Operand::Adr(Register::Rsp),
+                    initializer,
+                ));
                 self.make_operand_reg_available(&initializer);
 
-                self.current_stack_offset += 8;
+                self.current_stack_offset += init_size;
                 block.insts.push(Inst::UAdd(
                     Operand::Data(Register::Rsp),
-                    Operand::Imm(Imm::Int(8)),
+                    Operand::Imm(Imm::Int(init_size)),
                     Operand::Data(Register::Rsp),
                 ));
 
@@ -191,20 +255,49 @@ impl<'a> Generator<'a> {
     }
 
     fn gen_expression(&mut self, expr: &ast::Expr, block: &mut inst::Block) -> Operand {
+        if let Some(value) = super::negated_int_lit(self.file, expr) {
+            let typ = &expr.typ.as_ref().unwrap().kind;
+            return Operand::Imm(super::int_imm(value, typ));
+        }
+
         let expr_reg = match &expr.kind {
             ast::ExprKind::Unary(unary_expr) => {
-                let operand = self.gen_expression(&(*unary_expr.expr), block);
+                let operand = self.gen_expression(&unary_expr.expr, block);
                 let result_reg = self.get_tmp_reg();
 
                 match &unary_expr.op.kind {
                     token::TokenKind::Minus => match &unary_expr.expr.typ.as_ref().unwrap().kind {
                         ast::TypeKind::Prim(prim_type) => match &prim_type {
-                            ast::PrimType::Int(_) | ast::PrimType::UInt(_) => {
+                            ast::PrimType::Int(_) => {
+                                let sub = self.int_inst(Inst::Sub, Inst::SSubChecked);
                                 block.insts.push(Inst::Move(
                                     Operand::Data(result_reg),
                                     Operand::Imm(Imm::Int(0)),
                                 ));
-                                block.insts.push(Inst::Sub(
+                                block.insts.push(sub(
+                                    Operand::Data(result_reg),
+                                    Operand::Data(result_reg),
+                                    operand,
+                                ));
+                            }
+                            ast::PrimType::UInt(_) => {
+                                let sub = self.int_inst(Inst::Sub, Inst::USubChecked);
+                                block.insts.push(Inst::Move(
+                                    Operand::Data(result_reg),
+                                    Operand::Imm(Imm::Int(0)),
+                                ));
+                                block.insts.push(sub(
+                                    Operand::Data(result_reg),
+                                    Operand::Data(result_reg),
+                                    operand,
+                                ));
+                            }
+                            ast::PrimType::Float(32) => {
+                                block.insts.push(Inst::Move(
+                                    Operand::Data(result_reg),
+                                    Operand::Imm(Imm::Float(0.0)),
+                                ));
+                                block.insts.push(Inst::FSub32(
                                     Operand::Data(result_reg),
                                     Operand::Data(result_reg),
                                     operand,
@@ -233,13 +326,18 @@ impl<'a> Generator<'a> {
                     _ => unreachable!(),
                 }
 
+                if unary_expr.op.kind == token::TokenKind::Minus {
+                    let typ = &unary_expr.expr.typ.as_ref().unwrap().kind;
+                    self.wrap_to_width(typ, result_reg, block);
+                }
+
                 self.make_operand_reg_available(&operand);
 
                 Operand::Data(result_reg)
             }
             ast::ExprKind::Binary(binary_expr) => {
                 if binary_expr.op.kind == token::TokenKind::Equal {
-                    let right_value = self.gen_expression(&(*binary_expr.right), block);
+                    let right_value = self.gen_expression(&binary_expr.right, block);
                     if let ast::ExprKind::Let(let_expr) = &binary_expr.left.kind {
                         let resolved_stack_offset = *self
                             .namespace
@@ -256,9 +354,11 @@ impl<'a> Generator<'a> {
                             Operand::Data(Register::Rfp),
                             Operand::Data(adr_reg),
                         ));
-                        block
-                            .insts
-                            .push(Inst::Move(Operand::Adr(adr_reg), right_value));
+                        block.insts.push(move_inst(
+                            &binary_expr.left.typ.as_ref().unwrap().kind,
+                            Operand::Adr(adr_reg),
+                            right_value,
+                        ));
 
                         self.make_reg_available(&adr_reg);
                         right_value
@@ -266,7 +366,7 @@ impl<'a> Generator<'a> {
                         panic!("can only assign to variables")
                     }
                 } else if binary_expr.op.kind == token::TokenKind::Dot {
-                    let struct_pointer = match self.gen_expression(&(*binary_expr.left), block) {
+                    let struct_pointer = match self.gen_expression(&binary_expr.left, block) {
                         Operand::Adr(ptr_reg) => ptr_reg,
                         // We treat the daata register as an address as well (this makes codegen more lenient)
                         Operand::Data(ptr_reg) => ptr_reg,
@@ -275,14 +375,14 @@ impl<'a> Generator<'a> {
                         }
                     };
 
-                    let member_name =
-                        if let ast::ExprKind::Let(let_expr) = &(*binary_expr.right).kind {
-                            self.file.lexeme(&let_expr.ident.span)
-                        } else {
-                            unreachable!()
-                        };
+                    let member_name = if let ast::ExprKind::Let(let_expr) = &binary_expr.right.kind
+                    {
+                        self.file.lexeme(&let_expr.ident.span)
+                    } else {
+                        unreachable!()
+                    };
 
-                    let struct_type = if let Some(left_type) = &(*binary_expr.left).typ {
+                    let struct_type = if let Some(left_type) = &binary_expr.left.typ {
                         if let ast::TypeKind::Struct(struct_type) = &left_type.kind {
                             struct_type
                         } else {
@@ -292,16 +392,18 @@ impl<'a> Generator<'a> {
                         unreachable!()
                     };
 
-                    let member_offset = struct_type
+                    let member_type = struct_type
                         .members
                         .iter()
-                        .position(|(type_member_name, _)| type_member_name == member_name);
-                    if let Some(member_offset) = member_offset {
+                        .find(|(type_member_name, _)| type_member_name == member_name)
+                        .map(|(_, member_type)| &member_type.kind);
+                    let member_offset = super::member_offset(struct_type, member_name);
+                    if let (Some(member_type), Some(member_offset)) = (member_type, member_offset) {
                         let member_adr_reg = self.get_tmp_reg();
 
                         block.insts.push(Inst::Move(
                             Operand::Data(member_adr_reg),
-                            Operand::Imm(Imm::Int((member_offset * 8) as u64)),
+                            Operand::Imm(Imm::Int(member_offset)),
                         ));
                         block.insts.push(Inst::UAdd(
                             Operand::Data(member_adr_reg),
@@ -310,7 +412,8 @@ impl<'a> Generator<'a> {
                         ));
 
                         let value_reg = self.get_tmp_reg();
-                        block.insts.push(Inst::Move(
+                        block.insts.push(move_inst(
+                            member_type,
                             Operand::Data(value_reg),
                             Operand::Adr(member_adr_reg),
                         ));
@@ -323,8 +426,8 @@ impl<'a> Generator<'a> {
                         unreachable!()
                     }
                 } else {
-                    let left_value = self.gen_expression(&(*binary_expr.left), block);
-                    let right_value = self.gen_expression(&(*binary_expr.right), block);
+                    let left_value = self.gen_expression(&binary_expr.left, block);
+                    let right_value = self.gen_expression(&binary_expr.right, block);
                     let result_reg = self.get_tmp_reg();
 
                     if let ast::TypeKind::Prim(prim_type) =
@@ -333,14 +436,23 @@ impl<'a> Generator<'a> {
                         match &binary_expr.op.kind {
                             token::TokenKind::Plus => match &prim_type {
                                 ast::PrimType::Int(_) => {
-                                    block.insts.push(Inst::SAdd(
+                                    let add = self.int_inst(Inst::SAdd, Inst::SAddChecked);
+                                    block.insts.push(add(
                                         Operand::Data(result_reg),
                                         left_value,
                                         right_value,
                                     ));
                                 }
                                 ast::PrimType::UInt(_) => {
-                                    block.insts.push(Inst::UAdd(
+                                    let add = self.int_inst(Inst::UAdd, Inst::UAddChecked);
+                                    block.insts.push(add(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
+                                ast::PrimType::Float(32) => {
+                                    block.insts.push(Inst::FAdd32(
                                         Operand::Data(result_reg),
                                         left_value,
                                         right_value,
@@ -356,8 +468,24 @@ impl<'a> Generator<'a> {
                                 _ => unreachable!(),
                             },
                             token::TokenKind::Minus => match &prim_type {
-                                ast::PrimType::Int(_) | ast::PrimType::UInt(_) => {
-                                    block.insts.push(Inst::Sub(
+                                ast::PrimType::Int(_) => {
+                                    let sub = self.int_inst(Inst::Sub, Inst::SSubChecked);
+                                    block.insts.push(sub(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
+                                ast::PrimType::UInt(_) => {
+                                    let sub = self.int_inst(Inst::Sub, Inst::USubChecked);
+                                    block.insts.push(sub(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
+                                ast::PrimType::Float(32) => {
+                                    block.insts.push(Inst::FSub32(
                                         Operand::Data(result_reg),
                                         left_value,
                                         right_value,
@@ -374,14 +502,23 @@ impl<'a> Generator<'a> {
                             },
                             token::TokenKind::Star => match &prim_type {
                                 ast::PrimType::Int(_) => {
-                                    block.insts.push(Inst::SMul(
+                                    let mul = self.int_inst(Inst::SMul, Inst::SMulChecked);
+                                    block.insts.push(mul(
                                         Operand::Data(result_reg),
                                         left_value,
                                         right_value,
                                     ));
                                 }
                                 ast::PrimType::UInt(_) => {
-                                    block.insts.push(Inst::UMul(
+                                    let mul = self.int_inst(Inst::UMul, Inst::UMulChecked);
+                                    block.insts.push(mul(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
+                                ast::PrimType::Float(32) => {
+                                    block.insts.push(Inst::FMul32(
                                         Operand::Data(result_reg),
                                         left_value,
                                         right_value,
@@ -398,7 +535,8 @@ impl<'a> Generator<'a> {
                             },
                             token::TokenKind::Slash => match &prim_type {
                                 ast::PrimType::Int(_) => {
-                                    block.insts.push(Inst::SDiv(
+                                    let div = self.int_inst(Inst::SDiv, Inst::SDivChecked);
+                                    block.insts.push(div(
                                         Operand::Data(result_reg),
                                         left_value,
                                         right_value,
@@ -411,6 +549,13 @@ impl<'a> Generator<'a> {
                                         right_value,
                                     ));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block.insts.push(Inst::FDiv32(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block.insts.push(Inst::FDiv(
                                         Operand::Data(result_reg),
@@ -435,6 +580,13 @@ impl<'a> Generator<'a> {
                                         right_value,
                                     ));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block.insts.push(Inst::FRem32(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block.insts.push(Inst::FRem(
                                         Operand::Data(result_reg),
@@ -459,6 +611,13 @@ impl<'a> Generator<'a> {
                                         right_value,
                                     ));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block.insts.push(Inst::FLt32(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block.insts.push(Inst::FLt(
                                         Operand::Data(result_reg),
@@ -483,6 +642,13 @@ impl<'a> Generator<'a> {
                                         right_value,
                                     ));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block.insts.push(Inst::FGt32(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block.insts.push(Inst::FGt(
                                         Operand::Data(result_reg),
@@ -499,13 +665,22 @@ impl<'a> Generator<'a> {
                                 todo!()
                             }
                             token::TokenKind::EqualEqual => match &prim_type {
-                                ast::PrimType::Int(_) | ast::PrimType::UInt(_) => {
+                                ast::PrimType::Int(_)
+                                | ast::PrimType::UInt(_)
+                                | ast::PrimType::Bool => {
                                     block.insts.push(Inst::Eq(
                                         Operand::Data(result_reg),
                                         left_value,
                                         right_value,
                                     ));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block.insts.push(Inst::FEq32(
+                                        Operand::Data(result_reg),
+                                        left_value,
+                                        right_value,
+                                    ));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block.insts.push(Inst::FEq(
                                         Operand::Data(result_reg),
@@ -513,7 +688,6 @@ impl<'a> Generator<'a> {
                                         right_value,
                                     ));
                                 }
-                                _ => unreachable!(),
                             },
                             token::TokenKind::BangEqual => {
                                 todo!()
@@ -527,6 +701,17 @@ impl<'a> Generator<'a> {
                             _ => unreachable!(),
                         }
 
+                        if matches!(
+                            binary_expr.op.kind,
+                            token::TokenKind::Plus
+                                | token::TokenKind::Minus
+                                | token::TokenKind::Star
+                                | token::TokenKind::Slash
+                        ) {
+                            let typ = &binary_expr.left.typ.as_ref().unwrap().kind;
+                            self.wrap_to_width(typ, result_reg, block);
+                        }
+
                         // Since these can now be overwritten
                         self.make_operand_reg_available(&left_value);
                         self.make_operand_reg_available(&right_value);
@@ -555,9 +740,11 @@ impl<'a> Generator<'a> {
                 ));
 
                 let reg = self.get_tmp_reg();
-                block
-                    .insts
-                    .push(Inst::Move(Operand::Data(reg), Operand::Adr(adr_reg)));
+                block.insts.push(move_inst(
+                    &expr.typ.as_ref().unwrap().kind,
+                    Operand::Data(reg),
+                    Operand::Adr(adr_reg),
+                ));
                 self.make_reg_available(&adr_reg);
 
                 Operand::Data(reg)
@@ -629,21 +816,24 @@ impl<'a> Generator<'a> {
                         Operand::Data(Register::Rsp),
                     ));
 
-                    for (member_name, _) in &struct_type.members {
+                    for (member_name, member_type) in &struct_type.members {
                         let member_value = initializers[member_name.as_str()];
                         let member_value = self.gen_expression(member_value, block);
+                        let member_size = super::size_of(&member_type.kind);
 
-                        block
-                            .insts
-                            .push(Inst::Move(Operand::Adr(Register::Rsp), member_value));
+                        block.insts.push(move_inst(
+                            &member_type.kind,
+                            Operand::Adr(Register::Rsp),
+                            member_value,
+                        ));
                         block.insts.push(Inst::UAdd(
                             Operand::Data(Register::Rsp),
-                            Operand::Imm(Imm::Int(8)),
+                            Operand::Imm(Imm::Int(member_size)),
                             Operand::Data(Register::Rsp),
                         ));
 
                         self.make_operand_reg_available(&member_value);
-                        self.current_stack_offset += 8;
+                        self.current_stack_offset += member_size;
                     }
 
                     Operand::Data(struct_stack_offset_reg)
@@ -652,12 +842,14 @@ impl<'a> Generator<'a> {
                 }
             }
             ast::ExprKind::Lit(lit) => Operand::Imm(match &lit.token.kind {
-                token::TokenKind::Int => {
-                    Imm::Int(self.file.lexeme(&lit.token.span).parse::<i64>().unwrap() as u64)
-                }
-                token::TokenKind::Float => {
-                    Imm::Float(self.file.lexeme(&lit.token.span).parse::<f64>().unwrap())
-                }
+                token::TokenKind::Int => super::int_imm(
+                    self.file.lexeme(&lit.token.span).parse::<u64>().unwrap(),
+                    &expr.typ.as_ref().unwrap().kind,
+                ),
+                token::TokenKind::Float => super::float_imm(
+                    self.file.lexeme(&lit.token.span).parse::<f64>().unwrap(),
+                    &expr.typ.as_ref().unwrap().kind,
+                ),
                 token::TokenKind::True => Imm::True,
                 token::TokenKind::False => Imm::False,
                 _ => unreachable!(),
@@ -669,7 +861,12 @@ impl<'a> Generator<'a> {
 }
 
 pub fn gen(file: &ast::File) -> Vec<inst::Block> {
+    gen_with_arithmetic(file, Arithmetic::default())
+}
+
+pub fn gen_with_arithmetic(file: &ast::File, arithmetic: Arithmetic) -> Vec<inst::Block> {
     let mut generator = Generator {
+        arithmetic,
         current_stack_offset: 0,
         available_tmp_registers: vec![
             Register::R8,
@@ -704,33 +901,57 @@ pub fn gen(file: &ast::File) -> Vec<inst::Block> {
 
     // For debugging purposes
     for (type_name, built_in_type) in &typespace {
+        let syscall_id = match &built_in_type {
+            ast::TypeKind::Prim(prim_type) => match &prim_type {
+                ast::PrimType::Int(_) => syscall::PRINT_I64,
+                ast::PrimType::UInt(_) => syscall::PRINT_U64,
+                ast::PrimType::Float(float_size) => {
+                    if *float_size == 32 {
+                        syscall::PRINT_F32
+                    } else {
+                        syscall::PRINT_F64
+                    }
+                }
+                ast::PrimType::Bool => syscall::PRINT_BOOL,
+            },
+            _ => unreachable!(),
+        };
+
         generator.blocks.push(inst::Block {
             label: format!("print_{}", type_name),
             insts: vec![
-                inst::Inst::Move(
-                    Operand::Data(Register::R0),
-                    Operand::Imm(Imm::Int(match &built_in_type {
-                        ast::TypeKind::Prim(prim_type) => match &prim_type {
-                            ast::PrimType::Int(_) => 0,
-                            ast::PrimType::UInt(_) => 1,
-                            ast::PrimType::Float(float_size) => {
-                                if *float_size == 32 {
-                                    2
-                                } else {
-                                    3
-                                }
-                            }
-                            ast::PrimType::Bool => 4,
-                        },
-                        _ => unreachable!(),
-                    })),
-                ),
-                inst::Inst::SysCall(Operand::Data(Register::R0)),
+                // R0 holds the return address, so the ID is passed directly
+                inst::Inst::SysCall(Operand::Imm(Imm::Int(syscall_id))),
                 inst::Inst::Move(Operand::Data(Register::Rip), Operand::Data(Register::R0)),
             ],
         });
     }
 
+    for (type_name, syscall_id) in &[
+        ("u64", syscall::READ_U64),
+        ("i64", syscall::READ_I64),
+        ("f64", syscall::READ_F64),
+        ("bool", syscall::READ_BOOL),
+    ] {
+        let syscall_id = *syscall_id;
+        generator.blocks.push(inst::Block {
+            label: format!("read_{}", type_name),
+            insts: vec![
+                // The result is returned in R0, so the return address moves to R9
+                inst::Inst::Move(Operand::Data(Register::R9), Operand::Data(Register::R0)),
+                inst::Inst::SysCall(Operand::Imm(Imm::Int(syscall_id))),
+                inst::Inst::Move(Operand::Data(Register::R0), Operand::Data(Register::R1)),
+                inst::Inst::Move(Operand::Data(Register::Rip), Operand::Data(Register::R9)),
+            ],
+        });
+    }
+
+    // Halts the VM, so there's nothing to return to
+    generator.blocks.push(inst::Block {
+        label: "exit".into(),
+        insts: vec![inst::Inst::SysCall(Operand::Imm(Imm::Int(syscall::EXIT)))],
+    });
+
     for stmt in &file.stmts {
         generator.gen_stmt(stmt);
     }
@@ -1,2 +1,97 @@
+use crate::{ast, token};
+use isa::shared::{self, Imm};
+
 pub mod cisc;
 pub mod risc;
+
+// What integer arithmetic does when it overflows the width of its type.
+// `Wrapping` wraps around like two's complement and `Checked` traps with an
+// overflow error.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Arithmetic {
+    #[default]
+    Wrapping,
+    Checked,
+}
+
+// Bytes a value of `typ` takes in memory. Structs live on the stack and are
+// referred to through an 8 byte pointer
+fn size_of(typ: &ast::TypeKind) -> u64 {
+    match typ {
+        ast::TypeKind::Prim(ast::PrimType::Int(bits))
+        | ast::TypeKind::Prim(ast::PrimType::UInt(bits))
+        | ast::TypeKind::Prim(ast::PrimType::Float(bits)) => *bits as u64 / 8,
+        ast::TypeKind::Prim(ast::PrimType::Bool) => 1,
+        _ => 8,
+    }
+}
+
+// Signed integers are sign extended when loaded. So are bools, which keeps
+// `true` stored as all ones intact
+fn is_sign_extended(typ: &ast::TypeKind) -> bool {
+    matches!(
+        typ,
+        ast::TypeKind::Prim(ast::PrimType::Int(_)) | ast::TypeKind::Prim(ast::PrimType::Bool)
+    )
+}
+
+// Members are packed in declaration order without any padding
+fn member_offset(struct_type: &ast::StructType, member_name: &str) -> Option<u64> {
+    let mut offset = 0;
+    for (name, typ) in &struct_type.members {
+        if name == member_name {
+            return Some(offset);
+        }
+        offset += size_of(&typ.kind);
+    }
+
+    None
+}
+
+// Float literals are parsed as f64, the `32` float instructions expect f32
+// bits in the low half of the register instead
+fn float_imm(value: f64, typ: &ast::TypeKind) -> Imm {
+    if let ast::TypeKind::Prim(ast::PrimType::Float(32)) = typ {
+        Imm::Int((value as f32).to_bits() as u64)
+    } else {
+        Imm::Float(value)
+    }
+}
+
+// Width in bits and signedness of integers narrower than a register. The
+// ISAs only do 64-bit arithmetic, so their results have to be brought back to
+// this width after every operation
+fn narrow_int(typ: &ast::TypeKind) -> Option<(u64, bool)> {
+    match typ {
+        ast::TypeKind::Prim(ast::PrimType::Int(bits)) if *bits < 64 => Some((*bits as u64, true)),
+        ast::TypeKind::Prim(ast::PrimType::UInt(bits)) if *bits < 64 => Some((*bits as u64, false)),
+        _ => None,
+    }
+}
+
+// Int literals are parsed as u64, the analyzer has already checked that they
+// fit their type, so this only has to extend them to a full register
+fn int_imm(value: u64, typ: &ast::TypeKind) -> Imm {
+    Imm::Int(match narrow_int(typ) {
+        Some((bits, true)) => shared::sign_extend(value, bits / 8),
+        Some((bits, false)) => shared::zero_extend(value, bits / 8),
+        None => value,
+    })
+}
+
+// The value of a negated int literal, which is read as a whole. Negating the
+// literal after wrapping it would overflow for e.g. `-128` as an i8
+fn negated_int_lit(file: &ast::File, expr: &ast::Expr) -> Option<u64> {
+    if let ast::ExprKind::Unary(unary_expr) = &expr.kind {
+        if let ast::ExprKind::Lit(lit) = &unary_expr.expr.kind {
+            if unary_expr.op.kind == token::TokenKind::Minus
+                && lit.token.kind == token::TokenKind::Int
+            {
+                let value = file.lexeme(&lit.token.span).parse::<u64>().unwrap();
+                return Some(value.wrapping_neg());
+            }
+        }
+    }
+
+    None
+}
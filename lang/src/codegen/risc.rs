@@ -1,12 +1,38 @@
-use crate::{ast, token};
+use crate::{ast, codegen::Arithmetic, token};
 use isa::{
     risc::inst,
     shared::{Imm, Label, Register},
+    syscall,
 };
 use std::{borrow::Borrow, collections::HashMap, convert::TryInto};
 
+// Loads a value of `typ`, only reading as many bytes as it takes
+fn load_inst(typ: &ast::TypeKind, dst: Register, adr: Register) -> inst::Inst {
+    match (super::size_of(typ), super::is_sign_extended(typ)) {
+        (1, false) => inst::Inst::Load8(dst, adr),
+        (1, true) => inst::Inst::Load8S(dst, adr),
+        (2, false) => inst::Inst::Load16(dst, adr),
+        (2, true) => inst::Inst::Load16S(dst, adr),
+        (4, false) => inst::Inst::Load32(dst, adr),
+        (4, true) => inst::Inst::Load32S(dst, adr),
+        _ => inst::Inst::Load(dst, adr),
+    }
+}
+
+fn store_inst(typ: &ast::TypeKind, adr: Register, src: Register) -> inst::Inst {
+    match super::size_of(typ) {
+        1 => inst::Inst::Store8(adr, src),
+        2 => inst::Inst::Store16(adr, src),
+        4 => inst::Inst::Store32(adr, src),
+        _ => inst::Inst::Store(adr, src),
+    }
+}
+
+type IntInst = fn(Register, Register, Register) -> inst::Inst;
+
 #[derive(Debug)]
 struct Generator<'a> {
+    arithmetic: Arithmetic,
     current_stack_offset: u64,
     available_tmp_registers: Vec<Register>,
     blocks: Vec<inst::Block>,
@@ -15,8 +41,62 @@ struct Generator<'a> {
 }
 
 impl<'a> Generator<'a> {
+    // Checked arithmetic swaps in the variant that traps on overflow
+    fn int_inst(&self, wrapping: IntInst, checked: IntInst) -> IntInst {
+        match self.arithmetic {
+            Arithmetic::Wrapping => wrapping,
+            Arithmetic::Checked => checked,
+        }
+    }
+
+    // Brings the integer in `reg` back to the width of `typ`, masking it and
+    // sign extending it for signed types. In checked mode a result that didn't
+    // fit traps just like a 64-bit overflow
+    fn wrap_to_width(&mut self, typ: &ast::TypeKind, reg: Register, block: &mut inst::Block) {
+        let (bits, signed) = match super::narrow_int(typ) {
+            Some(narrow_int) => narrow_int,
+            None => return,
+        };
+
+        let unwrapped_reg = self.get_tmp_reg();
+        block.insts.push(inst::Inst::Copy(unwrapped_reg, reg));
+
+        let mask_reg = self.get_tmp_reg();
+        block
+            .insts
+            .push(inst::Inst::Rega(mask_reg, Imm::Int((1 << bits) - 1)));
+        block.insts.push(inst::Inst::And(reg, reg, mask_reg));
+        if signed {
+            // Flipping the sign bit and subtracting it again carries it into
+            // all the bits above
+            block
+                .insts
+                .push(inst::Inst::Rega(mask_reg, Imm::Int(1 << (bits - 1))));
+            block.insts.push(inst::Inst::Xor(reg, reg, mask_reg));
+            block.insts.push(inst::Inst::Sub(reg, reg, mask_reg));
+        }
+
+        if self.arithmetic == Arithmetic::Checked {
+            // Any bits that changed make adding the largest u64 overflow
+            block
+                .insts
+                .push(inst::Inst::Xor(unwrapped_reg, unwrapped_reg, reg));
+            block
+                .insts
+                .push(inst::Inst::Rega(mask_reg, Imm::Int(u64::MAX)));
+            block.insts.push(inst::Inst::UAddChecked(
+                unwrapped_reg,
+                unwrapped_reg,
+                mask_reg,
+            ));
+        }
+
+        self.make_reg_available(&mask_reg);
+        self.make_reg_available(&unwrapped_reg);
+    }
+
     fn get_tmp_reg(&mut self) -> Register {
-        let tmp_reg = if let Some(tmp_reg) = self.available_tmp_registers.get(0) {
+        let tmp_reg = if let Some(tmp_reg) = self.available_tmp_registers.first() {
             *tmp_reg
         } else {
             // I'm unsure if it's actually possible to reach here
@@ -49,7 +129,7 @@ impl<'a> Generator<'a> {
                     panic!("can only generate assembly for functions with less than 8 parameters")
                 }
 
-                for (i, (param_ident, _)) in fn_decl.parameters.iter().enumerate() {
+                for (i, (param_ident, param_type)) in fn_decl.parameters.iter().enumerate() {
                     let param_reg = Register::from_id((i + 1).try_into().unwrap());
 
                     let param_adr_reg = self.get_tmp_reg();
@@ -62,16 +142,18 @@ impl<'a> Generator<'a> {
                         Register::Rfp,
                         param_adr_reg,
                     ));
-                    fn_init_block
-                        .insts
-                        .push(inst::Inst::Store(param_adr_reg, param_reg));
+                    fn_init_block.insts.push(store_inst(
+                        &param_type.kind,
+                        param_adr_reg,
+                        param_reg,
+                    ));
                     self.make_reg_available(&param_adr_reg);
 
                     self.namespace.insert(
                         self.file.lexeme(&param_ident.span).to_string(),
                         self.current_stack_offset,
                     );
-                    self.current_stack_offset += 8;
+                    self.current_stack_offset += super::size_of(&param_type.kind);
                 }
 
                 self.blocks.push(fn_init_block);
@@ -101,6 +183,7 @@ impl<'a> Generator<'a> {
                 };
 
                 let initializer_reg = self.gen_expression(&let_stmt.init, &mut block);
+                let init_type = &let_stmt.init.typ.as_ref().unwrap().kind;
                 let last_idx = self.blocks.len() - 1;
 
                 self.namespace.insert(
@@ -118,11 +201,11 @@ impl<'a> Generator<'a> {
                     .push(inst::Inst::UAdd(adr_reg, Register::Rfp, adr_reg));
                 block
                     .insts
-                    .push(inst::Inst::Store(adr_reg, initializer_reg));
+                    .push(store_inst(init_type, adr_reg, initializer_reg));
 
                 self.make_reg_available(&adr_reg);
                 self.make_reg_available(&initializer_reg);
-                self.current_stack_offset += 8;
+                self.current_stack_offset += super::size_of(init_type);
                 self.blocks[last_idx] = block;
             }
             ast::StmtKind::If(_if_stmt) => todo!(),
@@ -187,17 +270,36 @@ impl<'a> Generator<'a> {
     }
 
     fn gen_expression(&mut self, expr: &ast::Expr, block: &mut inst::Block) -> Register {
+        if let Some(value) = super::negated_int_lit(self.file, expr) {
+            let reg = self.get_tmp_reg();
+            let int_value = super::int_imm(value, &expr.typ.as_ref().unwrap().kind);
+            block.insts.push(inst::Inst::Rega(reg, int_value));
+            return reg;
+        }
+
         let expr_reg = match &expr.kind {
             ast::ExprKind::Unary(unary_expr) => {
-                let operand_reg = self.gen_expression(&(*unary_expr.expr), block);
+                let operand_reg = self.gen_expression(&unary_expr.expr, block);
                 let result_reg = self.get_tmp_reg();
 
                 match &unary_expr.op.kind {
                     token::TokenKind::Minus => match &unary_expr.expr.typ.as_ref().unwrap().kind {
                         ast::TypeKind::Prim(prim_type) => match &prim_type {
-                            ast::PrimType::Int(_) | ast::PrimType::UInt(_) => {
+                            ast::PrimType::Int(_) => {
+                                let sub = self.int_inst(inst::Inst::Sub, inst::Inst::SSubChecked);
+                                block.insts.push(inst::Inst::Rega(result_reg, Imm::Int(0)));
+                                block.insts.push(sub(result_reg, result_reg, operand_reg));
+                            }
+                            ast::PrimType::UInt(_) => {
+                                let sub = self.int_inst(inst::Inst::Sub, inst::Inst::USubChecked);
                                 block.insts.push(inst::Inst::Rega(result_reg, Imm::Int(0)));
-                                block.insts.push(inst::Inst::Sub(
+                                block.insts.push(sub(result_reg, result_reg, operand_reg));
+                            }
+                            ast::PrimType::Float(32) => {
+                                block
+                                    .insts
+                                    .push(inst::Inst::Rega(result_reg, Imm::Float(0.0)));
+                                block.insts.push(inst::Inst::FSub32(
                                     result_reg,
                                     result_reg,
                                     operand_reg,
@@ -223,12 +325,17 @@ impl<'a> Generator<'a> {
                     _ => unreachable!(),
                 }
 
+                if unary_expr.op.kind == token::TokenKind::Minus {
+                    let typ = &unary_expr.expr.typ.as_ref().unwrap().kind;
+                    self.wrap_to_width(typ, result_reg, block);
+                }
+
                 self.make_reg_available(&operand_reg);
                 result_reg
             }
             ast::ExprKind::Binary(binary_expr) => {
                 if binary_expr.op.kind == token::TokenKind::Equal {
-                    let right_reg = self.gen_expression(&(*binary_expr.right), block);
+                    let right_reg = self.gen_expression(&binary_expr.right, block);
                     if let ast::ExprKind::Let(let_expr) = &binary_expr.left.kind {
                         let resolved_stack_offset = *self
                             .namespace
@@ -242,7 +349,11 @@ impl<'a> Generator<'a> {
                         block
                             .insts
                             .push(inst::Inst::UAdd(adr_reg, Register::Rfp, adr_reg));
-                        block.insts.push(inst::Inst::Store(adr_reg, right_reg));
+                        block.insts.push(store_inst(
+                            &binary_expr.left.typ.as_ref().unwrap().kind,
+                            adr_reg,
+                            right_reg,
+                        ));
 
                         self.make_reg_available(&adr_reg);
                         right_reg
@@ -250,15 +361,15 @@ impl<'a> Generator<'a> {
                         panic!("can only assign to variables")
                     }
                 } else if binary_expr.op.kind == token::TokenKind::Dot {
-                    let struct_pointer = self.gen_expression(&(*binary_expr.left), block);
-                    let member_name =
-                        if let ast::ExprKind::Let(let_expr) = &(*binary_expr.right).kind {
-                            self.file.lexeme(&let_expr.ident.span)
-                        } else {
-                            unreachable!()
-                        };
+                    let struct_pointer = self.gen_expression(&binary_expr.left, block);
+                    let member_name = if let ast::ExprKind::Let(let_expr) = &binary_expr.right.kind
+                    {
+                        self.file.lexeme(&let_expr.ident.span)
+                    } else {
+                        unreachable!()
+                    };
 
-                    let struct_type = if let Some(left_type) = &(*binary_expr.left).typ {
+                    let struct_type = if let Some(left_type) = &binary_expr.left.typ {
                         if let ast::TypeKind::Struct(struct_type) = &left_type.kind {
                             struct_type
                         } else {
@@ -268,17 +379,18 @@ impl<'a> Generator<'a> {
                         unreachable!()
                     };
 
-                    let member_offset = struct_type
+                    let member_type = struct_type
                         .members
                         .iter()
-                        .position(|(type_member_name, _)| type_member_name == member_name);
-                    if let Some(member_offset) = member_offset {
+                        .find(|(type_member_name, _)| type_member_name == member_name)
+                        .map(|(_, member_type)| &member_type.kind);
+                    let member_offset = super::member_offset(struct_type, member_name);
+                    if let (Some(member_type), Some(member_offset)) = (member_type, member_offset) {
                         let member_adr_reg = self.get_tmp_reg();
 
-                        block.insts.push(inst::Inst::Rega(
-                            member_adr_reg,
-                            Imm::Int((member_offset * 8) as u64),
-                        ));
+                        block
+                            .insts
+                            .push(inst::Inst::Rega(member_adr_reg, Imm::Int(member_offset)));
                         block.insts.push(inst::Inst::UAdd(
                             member_adr_reg,
                             struct_pointer,
@@ -288,7 +400,7 @@ impl<'a> Generator<'a> {
                         let value_reg = self.get_tmp_reg();
                         block
                             .insts
-                            .push(inst::Inst::Load(value_reg, member_adr_reg));
+                            .push(load_inst(member_type, value_reg, member_adr_reg));
 
                         self.make_reg_available(&member_adr_reg);
                         self.make_reg_available(&struct_pointer);
@@ -298,8 +410,8 @@ impl<'a> Generator<'a> {
                         unreachable!()
                     }
                 } else {
-                    let left_reg = self.gen_expression(&(*binary_expr.left), block);
-                    let right_reg = self.gen_expression(&(*binary_expr.right), block);
+                    let left_reg = self.gen_expression(&binary_expr.left, block);
+                    let right_reg = self.gen_expression(&binary_expr.right, block);
                     let result_reg = self.get_tmp_reg();
 
                     if let ast::TypeKind::Prim(prim_type) =
@@ -308,14 +420,19 @@ impl<'a> Generator<'a> {
                         match &binary_expr.op.kind {
                             token::TokenKind::Plus => match &prim_type {
                                 ast::PrimType::Int(_) => {
-                                    block
-                                        .insts
-                                        .push(inst::Inst::SAdd(result_reg, left_reg, right_reg));
+                                    let add =
+                                        self.int_inst(inst::Inst::SAdd, inst::Inst::SAddChecked);
+                                    block.insts.push(add(result_reg, left_reg, right_reg));
                                 }
                                 ast::PrimType::UInt(_) => {
+                                    let add =
+                                        self.int_inst(inst::Inst::UAdd, inst::Inst::UAddChecked);
+                                    block.insts.push(add(result_reg, left_reg, right_reg));
+                                }
+                                ast::PrimType::Float(32) => {
                                     block
                                         .insts
-                                        .push(inst::Inst::UAdd(result_reg, left_reg, right_reg));
+                                        .push(inst::Inst::FAdd32(result_reg, left_reg, right_reg));
                                 }
                                 ast::PrimType::Float(_) => {
                                     block
@@ -325,10 +442,20 @@ impl<'a> Generator<'a> {
                                 _ => unreachable!(),
                             },
                             token::TokenKind::Minus => match &prim_type {
-                                ast::PrimType::Int(_) | ast::PrimType::UInt(_) => {
+                                ast::PrimType::Int(_) => {
+                                    let sub =
+                                        self.int_inst(inst::Inst::Sub, inst::Inst::SSubChecked);
+                                    block.insts.push(sub(result_reg, left_reg, right_reg));
+                                }
+                                ast::PrimType::UInt(_) => {
+                                    let sub =
+                                        self.int_inst(inst::Inst::Sub, inst::Inst::USubChecked);
+                                    block.insts.push(sub(result_reg, left_reg, right_reg));
+                                }
+                                ast::PrimType::Float(32) => {
                                     block
                                         .insts
-                                        .push(inst::Inst::Sub(result_reg, left_reg, right_reg));
+                                        .push(inst::Inst::FSub32(result_reg, left_reg, right_reg));
                                 }
                                 ast::PrimType::Float(_) => {
                                     block
@@ -339,14 +466,19 @@ impl<'a> Generator<'a> {
                             },
                             token::TokenKind::Star => match &prim_type {
                                 ast::PrimType::Int(_) => {
-                                    block
-                                        .insts
-                                        .push(inst::Inst::SMul(result_reg, left_reg, right_reg));
+                                    let mul =
+                                        self.int_inst(inst::Inst::SMul, inst::Inst::SMulChecked);
+                                    block.insts.push(mul(result_reg, left_reg, right_reg));
                                 }
                                 ast::PrimType::UInt(_) => {
+                                    let mul =
+                                        self.int_inst(inst::Inst::UMul, inst::Inst::UMulChecked);
+                                    block.insts.push(mul(result_reg, left_reg, right_reg));
+                                }
+                                ast::PrimType::Float(32) => {
                                     block
                                         .insts
-                                        .push(inst::Inst::UMul(result_reg, left_reg, right_reg));
+                                        .push(inst::Inst::FMul32(result_reg, left_reg, right_reg));
                                 }
                                 ast::PrimType::Float(_) => {
                                     block
@@ -357,15 +489,20 @@ impl<'a> Generator<'a> {
                             },
                             token::TokenKind::Slash => match &prim_type {
                                 ast::PrimType::Int(_) => {
-                                    block
-                                        .insts
-                                        .push(inst::Inst::SDiv(result_reg, left_reg, right_reg));
+                                    let div =
+                                        self.int_inst(inst::Inst::SDiv, inst::Inst::SDivChecked);
+                                    block.insts.push(div(result_reg, left_reg, right_reg));
                                 }
                                 ast::PrimType::UInt(_) => {
                                     block
                                         .insts
                                         .push(inst::Inst::UDiv(result_reg, left_reg, right_reg));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block
+                                        .insts
+                                        .push(inst::Inst::FDiv32(result_reg, left_reg, right_reg));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block
                                         .insts
@@ -384,6 +521,11 @@ impl<'a> Generator<'a> {
                                         .insts
                                         .push(inst::Inst::URem(result_reg, left_reg, right_reg));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block
+                                        .insts
+                                        .push(inst::Inst::FRem32(result_reg, left_reg, right_reg));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block
                                         .insts
@@ -402,6 +544,11 @@ impl<'a> Generator<'a> {
                                         .insts
                                         .push(inst::Inst::ULt(result_reg, left_reg, right_reg));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block
+                                        .insts
+                                        .push(inst::Inst::FLt32(result_reg, left_reg, right_reg));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block
                                         .insts
@@ -420,6 +567,11 @@ impl<'a> Generator<'a> {
                                         .insts
                                         .push(inst::Inst::UGt(result_reg, left_reg, right_reg));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block
+                                        .insts
+                                        .push(inst::Inst::FGt32(result_reg, left_reg, right_reg));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block
                                         .insts
@@ -434,17 +586,23 @@ impl<'a> Generator<'a> {
                                 todo!()
                             }
                             token::TokenKind::EqualEqual => match &prim_type {
-                                ast::PrimType::Int(_) | ast::PrimType::UInt(_) => {
+                                ast::PrimType::Int(_)
+                                | ast::PrimType::UInt(_)
+                                | ast::PrimType::Bool => {
                                     block
                                         .insts
                                         .push(inst::Inst::Eq(result_reg, left_reg, right_reg));
                                 }
+                                ast::PrimType::Float(32) => {
+                                    block
+                                        .insts
+                                        .push(inst::Inst::FEq32(result_reg, left_reg, right_reg));
+                                }
                                 ast::PrimType::Float(_) => {
                                     block
                                         .insts
                                         .push(inst::Inst::FEq(result_reg, left_reg, right_reg));
                                 }
-                                _ => unreachable!(),
                             },
                             token::TokenKind::BangEqual => {
                                 todo!()
@@ -458,6 +616,17 @@ impl<'a> Generator<'a> {
                             _ => unreachable!(),
                         }
 
+                        if matches!(
+                            binary_expr.op.kind,
+                            token::TokenKind::Plus
+                                | token::TokenKind::Minus
+                                | token::TokenKind::Star
+                                | token::TokenKind::Slash
+                        ) {
+                            let typ = &binary_expr.left.typ.as_ref().unwrap().kind;
+                            self.wrap_to_width(typ, result_reg, block);
+                        }
+
                         // Since these can now be overwritten
                         self.make_reg_available(&left_reg);
                         self.make_reg_available(&right_reg);
@@ -482,7 +651,9 @@ impl<'a> Generator<'a> {
                     .push(inst::Inst::UAdd(adr_reg, Register::Rfp, adr_reg));
 
                 let reg = self.get_tmp_reg();
-                block.insts.push(inst::Inst::Load(reg, adr_reg));
+                block
+                    .insts
+                    .push(load_inst(&expr.typ.as_ref().unwrap().kind, reg, adr_reg));
                 self.make_reg_available(&adr_reg);
                 reg
             }
@@ -559,7 +730,7 @@ impl<'a> Generator<'a> {
                         struct_stack_offset_reg,
                     ));
 
-                    for (member_name, _) in &struct_type.members {
+                    for (member_name, member_type) in &struct_type.members {
                         let member_value = initializers[member_name.as_str()];
                         let member_reg = self.gen_expression(member_value, block);
 
@@ -575,11 +746,11 @@ impl<'a> Generator<'a> {
                         ));
                         block
                             .insts
-                            .push(inst::Inst::Store(member_adr_reg, member_reg));
+                            .push(store_inst(&member_type.kind, member_adr_reg, member_reg));
 
                         self.make_reg_available(&member_adr_reg);
                         self.make_reg_available(&member_reg);
-                        self.current_stack_offset += 8;
+                        self.current_stack_offset += super::size_of(&member_type.kind);
                     }
 
                     struct_stack_offset_reg
@@ -592,14 +763,17 @@ impl<'a> Generator<'a> {
 
                 match &lit.token.kind {
                     token::TokenKind::Int => {
-                        let int_value = Imm::Int(
-                            self.file.lexeme(&lit.token.span).parse::<i64>().unwrap() as u64,
+                        let int_value = super::int_imm(
+                            self.file.lexeme(&lit.token.span).parse::<u64>().unwrap(),
+                            &expr.typ.as_ref().unwrap().kind,
                         );
                         block.insts.push(inst::Inst::Rega(reg, int_value));
                     }
                     token::TokenKind::Float => {
-                        let float_value =
-                            Imm::Float(self.file.lexeme(&lit.token.span).parse::<f64>().unwrap());
+                        let float_value = super::float_imm(
+                            self.file.lexeme(&lit.token.span).parse::<f64>().unwrap(),
+                            &expr.typ.as_ref().unwrap().kind,
+                        );
                         block.insts.push(inst::Inst::Rega(reg, float_value));
                     }
                     token::TokenKind::True => {
@@ -620,7 +794,12 @@ impl<'a> Generator<'a> {
 }
 
 pub fn gen(file: &ast::File) -> Vec<inst::Block> {
+    gen_with_arithmetic(file, Arithmetic::default())
+}
+
+pub fn gen_with_arithmetic(file: &ast::File, arithmetic: Arithmetic) -> Vec<inst::Block> {
     let mut generator = Generator {
+        arithmetic,
         current_stack_offset: 0,
         available_tmp_registers: vec![
             Register::R8,
@@ -656,33 +835,62 @@ pub fn gen(file: &ast::File) -> Vec<inst::Block> {
 
     // For debugging purposes
     for (type_name, built_in_type) in &typespace {
+        let syscall_id = match &built_in_type {
+            ast::TypeKind::Prim(prim_type) => match &prim_type {
+                ast::PrimType::Int(_) => syscall::PRINT_I64,
+                ast::PrimType::UInt(_) => syscall::PRINT_U64,
+                ast::PrimType::Float(float_size) => {
+                    if *float_size == 32 {
+                        syscall::PRINT_F32
+                    } else {
+                        syscall::PRINT_F64
+                    }
+                }
+                ast::PrimType::Bool => syscall::PRINT_BOOL,
+            },
+            _ => unreachable!(),
+        };
+
         generator.blocks.push(inst::Block {
             label: format!("print_{}", type_name),
             insts: vec![
-                inst::Inst::Rega(
-                    Register::R0,
-                    Imm::Int(match &built_in_type {
-                        ast::TypeKind::Prim(prim_type) => match &prim_type {
-                            ast::PrimType::Int(_) => 0,
-                            ast::PrimType::UInt(_) => 1,
-                            ast::PrimType::Float(float_size) => {
-                                if *float_size == 32 {
-                                    2
-                                } else {
-                                    3
-                                }
-                            }
-                            ast::PrimType::Bool => 4,
-                        },
-                        _ => unreachable!(),
-                    }),
-                ),
-                inst::Inst::SysCall(Register::R0),
+                // R0 holds the return address, so the ID goes in a temporary
+                inst::Inst::Rega(Register::R9, Imm::Int(syscall_id)),
+                inst::Inst::SysCall(Register::R9),
                 inst::Inst::Copy(Register::Rip, Register::R0),
             ],
         });
     }
 
+    for (type_name, syscall_id) in &[
+        ("u64", syscall::READ_U64),
+        ("i64", syscall::READ_I64),
+        ("f64", syscall::READ_F64),
+        ("bool", syscall::READ_BOOL),
+    ] {
+        let syscall_id = *syscall_id;
+        generator.blocks.push(inst::Block {
+            label: format!("read_{}", type_name),
+            insts: vec![
+                // The result is returned in R0, so the return address moves to R9
+                inst::Inst::Copy(Register::R9, Register::R0),
+                inst::Inst::Rega(Register::R10, Imm::Int(syscall_id)),
+                inst::Inst::SysCall(Register::R10),
+                inst::Inst::Copy(Register::R0, Register::R1),
+                inst::Inst::Copy(Register::Rip, Register::R9),
+            ],
+        });
+    }
+
+    // Halts the VM, so there's nothing to return to
+    generator.blocks.push(inst::Block {
+        label: "exit".into(),
+        insts: vec![
+            inst::Inst::Rega(Register::R9, Imm::Int(syscall::EXIT)),
+            inst::Inst::SysCall(Register::R9),
+        ],
+    });
+
     for stmt in &file.stmts {
         generator.gen_stmt(stmt);
     }
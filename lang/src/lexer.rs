@@ -3,6 +3,7 @@ use crate::{
     token::{Token, TokenKind},
 };
 
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 struct Lexer {
     source: Vec<char>,
@@ -211,7 +212,7 @@ impl Lexer {
             }
 
             _ => {
-                if c.is_digit(10) {
+                if c.is_ascii_digit() {
                     Ok(Some(self.lex_number()?))
                 } else if c.is_alphabetic() {
                     Ok(Some(self.lex_ident()?))
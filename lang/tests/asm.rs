@@ -0,0 +1,64 @@
+mod common;
+
+use isa::{cisc, memory::Memory, risc};
+use lang::codegen;
+use std::io;
+
+// What a program printed, its exit code and how many instructions it took
+type Run = (String, i64, u64);
+
+const ROUND_TRIP_OUTPUT: &str = "35\ntrue\n144\n0.75\n0.75\ntrue\nfalse\n";
+
+fn risc_asm(blocks: &[risc::inst::Block]) -> String {
+    blocks.iter().map(|block| block.as_asm()).collect()
+}
+
+fn run_risc(blocks: &[risc::inst::Block]) -> Run {
+    let mut output = Vec::new();
+    let status =
+        risc::vm::VM::new(blocks, io::empty(), &mut output, Memory::flat(1 << 16)).interpret();
+    (
+        String::from_utf8(output).unwrap(),
+        status.code,
+        status.insts(),
+    )
+}
+
+fn cisc_asm(blocks: &[cisc::inst::Block]) -> String {
+    blocks.iter().map(|block| block.as_asm()).collect()
+}
+
+fn run_cisc(blocks: &[cisc::inst::Block]) -> Run {
+    let mut output = Vec::new();
+    let status =
+        cisc::vm::VM::new(blocks, io::empty(), &mut output, Memory::flat(1 << 16)).interpret();
+    (
+        String::from_utf8(output).unwrap(),
+        status.code,
+        status.insts(),
+    )
+}
+
+#[test]
+fn risc_asm_round_trips() {
+    let blocks = codegen::risc::gen(&common::compile("round_trip"));
+    let asm = risc_asm(&blocks);
+    let parsed = risc::asm::parse(&asm).unwrap();
+    assert_eq!(risc_asm(&parsed), asm);
+
+    let run = run_risc(&blocks);
+    assert_eq!(run.0, ROUND_TRIP_OUTPUT);
+    assert_eq!(run_risc(&parsed), run);
+}
+
+#[test]
+fn cisc_asm_round_trips() {
+    let blocks = codegen::cisc::gen(&common::compile("round_trip"));
+    let asm = cisc_asm(&blocks);
+    let parsed = cisc::asm::parse(&asm).unwrap();
+    assert_eq!(cisc_asm(&parsed), asm);
+
+    let run = run_cisc(&blocks);
+    assert_eq!(run.0, ROUND_TRIP_OUTPUT);
+    assert_eq!(run_cisc(&parsed), run);
+}
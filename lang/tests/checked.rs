@@ -0,0 +1,128 @@
+mod common;
+
+use isa::{
+    memory::Memory,
+    shared::{HaltReason, TrapReason},
+};
+use lang::{codegen, codegen::Arithmetic};
+use std::io;
+
+const I64_VALUES: &str = "
+    let max i64 = 9223372036854775807
+    let min i64 = -9223372036854775808
+    let one i64 = 1
+    let two i64 = 2
+    let neg i64 = -1";
+
+const U64_VALUES: &str = "
+    let zero u64 = 0
+    let one u64 = 1
+    let two u64 = 2
+    let max u64 = 18446744073709551615";
+
+// Prints `expr` after defining `values`, returning what both VMs printed or the
+// reason they trapped
+fn run(
+    values: &str,
+    print: &str,
+    expr: &str,
+    arithmetic: Arithmetic,
+) -> Result<String, TrapReason> {
+    let source = format!("fn main() {{{}\n    {}({})\n}}\n", values, print, expr);
+    let file = common::compile_source(&source);
+
+    let mut risc_output = Vec::new();
+    let blocks = codegen::risc::gen_with_arithmetic(&file, arithmetic);
+    let risc_status = isa::risc::vm::VM::new(
+        &blocks,
+        io::empty(),
+        &mut risc_output,
+        Memory::flat(1 << 16),
+    )
+    .interpret();
+
+    let mut cisc_output = Vec::new();
+    let blocks = codegen::cisc::gen_with_arithmetic(&file, arithmetic);
+    let cisc_status = isa::cisc::vm::VM::new(
+        &blocks,
+        io::empty(),
+        &mut cisc_output,
+        Memory::flat(1 << 16),
+    )
+    .interpret();
+
+    assert_eq!(risc_output, cisc_output, "{}", expr);
+    match (risc_status.reason, cisc_status.reason) {
+        (HaltReason::Returned, HaltReason::Returned) => Ok(String::from_utf8(risc_output).unwrap()),
+        (HaltReason::Trapped(risc_err), HaltReason::Trapped(cisc_err)) => {
+            assert_eq!(risc_err.reason, cisc_err.reason, "{}", expr);
+            Err(risc_err.reason)
+        }
+        reasons => panic!("`{}` halted differently: {:?}", expr, reasons),
+    }
+}
+
+// Each expression overflows 64 bits, wrapping around to the output given
+fn assert_checked(values: &str, print: &str, cases: &[(&str, &str)]) {
+    for (expr, wrapped) in cases {
+        assert_eq!(
+            run(values, print, expr, Arithmetic::Wrapping),
+            Ok(format!("{}\n", wrapped)),
+            "{}",
+            expr
+        );
+        assert_eq!(
+            run(values, print, expr, Arithmetic::Checked),
+            Err(TrapReason::Overflow),
+            "{}",
+            expr
+        );
+    }
+}
+
+#[test]
+fn checked_i64_arithmetic_traps_where_wrapping_wraps() {
+    assert_checked(
+        I64_VALUES,
+        "print_i64",
+        &[
+            ("max + one", "-9223372036854775808"),
+            ("min - one", "9223372036854775807"),
+            ("max * two", "-2"),
+            ("min / neg", "-9223372036854775808"),
+            ("-min", "-9223372036854775808"),
+        ],
+    );
+}
+
+#[test]
+fn checked_u64_arithmetic_traps_where_wrapping_wraps() {
+    assert_checked(
+        U64_VALUES,
+        "print_u64",
+        &[
+            ("max + one", "0"),
+            ("zero - one", "18446744073709551615"),
+            ("max * two", "18446744073709551614"),
+        ],
+    );
+}
+
+#[test]
+fn checked_arithmetic_allows_results_in_range() {
+    for arithmetic in [Arithmetic::Wrapping, Arithmetic::Checked].iter().copied() {
+        assert_eq!(
+            run(
+                I64_VALUES,
+                "print_i64",
+                "min + max * one - neg / two",
+                arithmetic
+            ),
+            Ok("-1\n".into())
+        );
+        assert_eq!(
+            run(U64_VALUES, "print_u64", "max - one * two", arithmetic),
+            Ok("18446744073709551613\n".into())
+        );
+    }
+}
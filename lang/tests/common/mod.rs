@@ -0,0 +1,45 @@
+// Not every test uses every helper
+#![allow(dead_code)]
+
+use lang::{analyzer, ast, common::Error, lexer, parser};
+use std::path::PathBuf;
+
+// Reads, parses and analyzes one of the programs in `tests/programs`
+pub fn compile(program: &str) -> ast::File {
+    let path = format!(
+        "{}/tests/programs/{}.lang",
+        env!("CARGO_MANIFEST_DIR"),
+        program
+    );
+    analyze(parse(ast::File::new(path).unwrap()))
+}
+
+// Same as `compile` for a program built by the test itself
+pub fn compile_source(source: &str) -> ast::File {
+    analyze(parse_source(source))
+}
+
+// The error the analyzer rejects `source` with
+pub fn analysis_error(source: &str) -> Error {
+    let mut file = parse_source(source);
+    analyzer::analyze_mut(&mut file).unwrap_err()
+}
+
+fn parse_source(source: &str) -> ast::File {
+    parse(ast::File {
+        path: PathBuf::from("test.lang"),
+        source: source.to_string(),
+        stmts: Vec::new(),
+    })
+}
+
+fn parse(mut file: ast::File) -> ast::File {
+    let tokens = lexer::lex(&file.source).unwrap();
+    file.stmts = parser::parse(&tokens).unwrap();
+    file
+}
+
+fn analyze(mut file: ast::File) -> ast::File {
+    analyzer::analyze_mut(&mut file).unwrap();
+    file
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use isa::{memory::Memory, shared::HaltReason};
+use lang::codegen;
+use std::io;
+
+#[test]
+fn exit_stops_the_program_with_its_code() {
+    let file = common::compile("exit");
+
+    let mut output = Vec::new();
+    let blocks = codegen::risc::gen(&file);
+    let status = isa::risc::vm::VM::new(&blocks, io::empty(), &mut output, Memory::flat(1 << 16))
+        .interpret();
+    assert_eq!(status.reason, HaltReason::Exited);
+    assert_eq!(status.code, -3);
+    assert_eq!(output, b"1\n");
+
+    let mut output = Vec::new();
+    let blocks = codegen::cisc::gen(&file);
+    let status = isa::cisc::vm::VM::new(&blocks, io::empty(), &mut output, Memory::flat(1 << 16))
+        .interpret();
+    assert_eq!(status.reason, HaltReason::Exited);
+    assert_eq!(status.code, -3);
+    assert_eq!(output, b"1\n");
+}
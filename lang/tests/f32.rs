@@ -0,0 +1,31 @@
+mod common;
+
+use isa::memory::Memory;
+use lang::codegen;
+use std::io;
+
+// f32 values lose the 1 that f64 keeps
+const OUTPUT: &str = "16777216\n16777217\n-0.25\ntrue\n";
+
+#[test]
+fn f32_values_use_the_f32_instructions() {
+    let file = common::compile("f32");
+
+    let blocks = codegen::risc::gen(&file);
+    let asm: String = blocks.iter().map(|block| block.as_asm()).collect();
+    assert!(asm.contains("fadd32") && asm.contains("fdiv32") && asm.contains("fgt32"));
+    let mut output = Vec::new();
+    let status = isa::risc::vm::VM::new(&blocks, io::empty(), &mut output, Memory::flat(1 << 16))
+        .interpret();
+    assert!(status.trap().is_none());
+    assert_eq!(String::from_utf8(output).unwrap(), OUTPUT);
+
+    let blocks = codegen::cisc::gen(&file);
+    let asm: String = blocks.iter().map(|block| block.as_asm()).collect();
+    assert!(asm.contains("fadd32") && asm.contains("fdiv32") && asm.contains("fgt32"));
+    let mut output = Vec::new();
+    let status = isa::cisc::vm::VM::new(&blocks, io::empty(), &mut output, Memory::flat(1 << 16))
+        .interpret();
+    assert!(status.trap().is_none());
+    assert_eq!(String::from_utf8(output).unwrap(), OUTPUT);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use isa::memory::Memory;
+use lang::{ast, codegen};
+
+const INPUT: &str = "41\n-21\n5.0\nfalse\n";
+const OUTPUT: &str = "42\n-42\n2.5\nfalse\n";
+
+// The output of `file` on the RISC and CISC VMs
+fn run(file: &ast::File, input: &str) -> [String; 2] {
+    let mut risc_output = Vec::new();
+    let blocks = codegen::risc::gen(file);
+    let status = isa::risc::vm::VM::new(
+        &blocks,
+        input.as_bytes(),
+        &mut risc_output,
+        Memory::flat(1 << 16),
+    )
+    .interpret();
+    assert!(status.trap().is_none());
+
+    let mut cisc_output = Vec::new();
+    let blocks = codegen::cisc::gen(file);
+    let status = isa::cisc::vm::VM::new(
+        &blocks,
+        input.as_bytes(),
+        &mut cisc_output,
+        Memory::flat(1 << 16),
+    )
+    .interpret();
+    assert!(status.trap().is_none());
+
+    [
+        String::from_utf8(risc_output).unwrap(),
+        String::from_utf8(cisc_output).unwrap(),
+    ]
+}
+
+#[test]
+fn read_built_ins_parse_the_input() {
+    let file = common::compile("input");
+    assert_eq!(run(&file, INPUT), [OUTPUT, OUTPUT]);
+}
+
+#[test]
+fn read_bool_matches_the_bool_literals() {
+    let file = common::compile_source(
+        "fn main() {
+    let a = read_bool()
+    let b = read_bool()
+    print_bool(a == true)
+    print_bool(b == false)
+}",
+    );
+    assert_eq!(
+        run(&file, "true\nfalse\n"),
+        ["true\ntrue\n", "true\ntrue\n"]
+    );
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use isa::{
+    memory::Memory,
+    shared::{ExitStatus, HaltReason, TrapReason},
+};
+use lang::{codegen, codegen::Arithmetic};
+use std::io;
+
+// Runs `program` on both VMs, checking they agree on what it printed and how it
+// halted
+fn run(program: &str, arithmetic: Arithmetic) -> (String, ExitStatus) {
+    let file = common::compile(program);
+
+    let mut risc_output = Vec::new();
+    let blocks = codegen::risc::gen_with_arithmetic(&file, arithmetic);
+    let risc_status = isa::risc::vm::VM::new(
+        &blocks,
+        io::empty(),
+        &mut risc_output,
+        Memory::flat(1 << 16),
+    )
+    .interpret();
+
+    let mut cisc_output = Vec::new();
+    let blocks = codegen::cisc::gen_with_arithmetic(&file, arithmetic);
+    let cisc_status = isa::cisc::vm::VM::new(
+        &blocks,
+        io::empty(),
+        &mut cisc_output,
+        Memory::flat(1 << 16),
+    )
+    .interpret();
+
+    assert_eq!(risc_output, cisc_output);
+    assert_eq!(risc_status.code, cisc_status.code);
+    assert_eq!(
+        risc_status.trap().map(|err| &err.reason),
+        cisc_status.trap().map(|err| &err.reason)
+    );
+
+    (String::from_utf8(risc_output).unwrap(), risc_status)
+}
+
+fn assert_wraps(program: &str, expected: &[&str]) {
+    let (output, status) = run(program, Arithmetic::Wrapping);
+    assert_eq!(status.reason, HaltReason::Returned);
+    assert_eq!(output.lines().collect::<Vec<_>>(), expected);
+
+    // Every program overflows its width straight away
+    let (output, status) = run(program, Arithmetic::Checked);
+    assert_eq!(status.trap().unwrap().reason, TrapReason::Overflow);
+    assert!(output.is_empty());
+}
+
+#[test]
+fn i8_wraps() {
+    assert_wraps("i8", &["-128", "127", "125", "-128", "-128", "true"]);
+}
+
+#[test]
+fn i16_wraps() {
+    assert_wraps(
+        "i16",
+        &["-32768", "32767", "32765", "-32768", "-32768", "true"],
+    );
+}
+
+#[test]
+fn i32_wraps() {
+    assert_wraps(
+        "i32",
+        &[
+            "-2147483648",
+            "2147483647",
+            "2147483645",
+            "-2147483648",
+            "-2147483648",
+            "true",
+        ],
+    );
+}
+
+#[test]
+fn i64_wraps() {
+    assert_wraps(
+        "i64",
+        &[
+            "-9223372036854775808",
+            "9223372036854775807",
+            "9223372036854775805",
+            "-9223372036854775808",
+            "-9223372036854775808",
+            "true",
+        ],
+    );
+}
+
+#[test]
+fn u8_wraps() {
+    assert_wraps("u8", &["255", "0", "1", "126", "1", "true"]);
+}
+
+#[test]
+fn u16_wraps() {
+    assert_wraps("u16", &["65535", "0", "1", "32766", "1", "true"]);
+}
+
+#[test]
+fn u32_wraps() {
+    assert_wraps("u32", &["4294967295", "0", "1", "2147483646", "1", "true"]);
+}
+
+#[test]
+fn u64_wraps() {
+    assert_wraps(
+        "u64",
+        &[
+            "18446744073709551615",
+            "0",
+            "1",
+            "9223372036854775806",
+            "1",
+            "true",
+        ],
+    );
+}
+
+#[test]
+fn checked_allows_values_in_range() {
+    let (output, status) = run("in_range", Arithmetic::Checked);
+    assert_eq!(status.reason, HaltReason::Returned);
+    assert_eq!(
+        output.lines().collect::<Vec<_>>(),
+        ["-1", "255", "-16384", "4294901760"]
+    );
+}
+
+fn let_source(typ: &str, lit: &str) -> String {
+    format!("fn main() {{\n    let x {} = {}\n}}\n", typ, lit)
+}
+
+#[test]
+fn int_literals_have_to_fit_their_type() {
+    for (typ, lit) in &[
+        ("u8", "256"),
+        ("u8", "-1"),
+        ("i8", "128"),
+        ("i8", "-129"),
+        ("i64", "9223372036854775808"),
+        ("u64", "18446744073709551616"),
+    ] {
+        let err = common::analysis_error(&let_source(typ, lit));
+        assert_eq!(
+            err.message,
+            format!("integer literal is out of range for {}", typ)
+        );
+    }
+
+    // The limits themselves are fine
+    for (typ, lit) in &[
+        ("u8", "255"),
+        ("i8", "-128"),
+        ("i64", "-9223372036854775808"),
+        ("u64", "18446744073709551615"),
+    ] {
+        common::compile_source(&let_source(typ, lit));
+    }
+}